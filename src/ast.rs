@@ -1,6 +1,19 @@
 use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::HashMap,
+    convert::TryFrom,
     fmt::{Display, Formatter},
     ops::BitAnd,
+    rc::Rc,
+};
+
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
+
+use crate::{
+    heap::{Heap, HeapRef},
+    source::Span,
 };
 
 #[derive(Debug, Clone)]
@@ -27,82 +40,241 @@ impl Display for Identifier {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+/// The character B uses to terminate strings, written `*e`
+pub const END_OF_STRING: char = '\x04';
+
+/// Resolves B's `*` escapes in the body of a string or character literal
+pub fn unescape(literal: &str) -> String {
+    let mut result = String::with_capacity(literal.len());
+    let mut chars = literal.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '*' {
+            result.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('0') => result.push('\0'),
+            Some('e') => result.push(END_OF_STRING),
+            Some('(') => result.push('{'),
+            Some(')') => result.push('}'),
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('*'),
+        }
+    }
+    result
+}
+
+/// Writes `s` the way it would appear inside a B string literal
+pub fn escape(s: &str) -> String {
+    s.chars()
+        .map(|ch| match ch {
+            '\0' => "*0".into(),
+            END_OF_STRING => "*e".into(),
+            '{' => "*(".into(),
+            '}' => "*)".into(),
+            '\t' => "*t".into(),
+            '\n' => "*n".into(),
+            '*' | '"' | '\'' => format!("*{}", ch),
+            ch => ch.to_string(),
+        })
+        .collect()
+}
+
+/// Packs the characters of a character constant like `'ab'` into a word,
+/// the first character ending up in the most significant byte
+pub fn pack_chars(chars: &str) -> i64 {
+    chars
+        .chars()
+        .fold(0, |word, ch| (word << 8) | (ch as i64 & 0xff))
+}
+
+/// The part of `s` before its `*e` terminator, or all of it if it has none
+pub fn terminated(s: &str) -> &str {
+    s.split(END_OF_STRING).next().unwrap_or("")
+}
+
+/// A key of a map, maps are indexed by integers and strings only
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Integer(i64),
+    BigInteger(BigInt),
+    String(String),
+}
+impl MapKey {
+    pub fn from_const(c: &Const) -> Option<Self> {
+        match c {
+            Const::Integer(i) => Some(Self::Integer(*i)),
+            Const::BigInteger(i) => Some(Self::BigInteger(i.clone())),
+            Const::String(s) => Some(Self::String(terminated(s).into())),
+            _ => None,
+        }
+    }
+
+    pub fn to_const(&self) -> Const {
+        match self {
+            Self::Integer(i) => Const::Integer(*i),
+            Self::BigInteger(i) => Const::BigInteger(i.clone()),
+            Self::String(s) => Const::String(s.clone()),
+        }
+    }
+}
+
+/// Maps are shared, every copy of one sees what is set through the others
+pub type Map = Rc<RefCell<HashMap<MapKey, Const>>>;
+
+#[derive(Debug, Clone)]
 pub enum Const {
     Integer(i64),
+    // Only holds values that do not fit in an i64, see `Const::from_big`
+    BigInteger(BigInt),
     String(String),
     Vector(Vec<Box<Expression>>),
+    HeapVector(HeapRef),
+    Map(Map),
     Ident(Identifier),
+    // What a builtin gives back when it fails in a way the program may want
+    // to handle, such as `eval` of malformed source
+    Error(String),
+    // What an `auto` holds before it is assigned when they are poisoned, the
+    // name it was declared as and where
+    Poison(Identifier, Span),
 }
 impl Const {
     pub fn truthy(&self) -> bool {
         match self {
             Self::Integer(i) => *i != 0,
+            Self::BigInteger(i) => !i.is_zero(),
             Self::String(s) => s.len() != 0,
             Self::Vector(v) => v.len() != 0,
             _ => true,
         }
     }
 
-    pub fn and(&self, rhs: &Self) -> Self {
-        match (self, rhs) {
-            (Self::Integer(lhs), Self::Integer(rhs)) => Self::Integer(lhs & rhs),
-            (lhs, rhs) => Self::Integer((lhs.truthy() && rhs.truthy()) as i64),
+    /// An integer of any size, kept as an `Integer` whenever it fits so the
+    /// two never hold the same value
+    pub fn from_big(i: BigInt) -> Self {
+        match i.to_i64() {
+            Some(i) => Self::Integer(i),
+            None => Self::BigInteger(i),
         }
     }
-    pub fn or(&self, rhs: &Self) -> Self {
-        match (self, rhs) {
-            (Self::Integer(lhs), Self::Integer(rhs)) => Self::Integer(lhs | rhs),
-            (lhs, rhs) => Self::Integer((lhs.truthy() || rhs.truthy()) as i64),
+
+    pub fn big(&self) -> Option<BigInt> {
+        match self {
+            Self::Integer(i) => Some(BigInt::from(*i)),
+            Self::BigInteger(i) => Some(i.clone()),
+            _ => None,
         }
     }
-    pub fn xor(&self, rhs: &Self) -> Self {
-        match (self, rhs) {
-            (Self::Integer(lhs), Self::Integer(rhs)) => Self::Integer(lhs ^ rhs),
-            (lhs, rhs) => Self::Integer((lhs.truthy() ^ rhs.truthy()) as i64),
+
+    /// Truncates an integer to the low `bits` bits of its two's complement,
+    /// which is what the arithmetic would have given on a machine word that
+    /// wide
+    pub fn wrapped(self, bits: u32) -> Self {
+        let shift = 64 - bits;
+        match self {
+            Self::Integer(i) => Self::Integer((i << shift) >> shift),
+            Self::BigInteger(i) => {
+                let low = i & BigInt::from(u64::MAX);
+                Self::Integer(((low.to_u64().unwrap() as i64) << shift) >> shift)
+            }
+            other => other,
         }
     }
-    pub fn shr(&self, rhs: &Self) -> Self {
-        match (self, rhs) {
-            (Self::Integer(lhs), Self::Integer(rhs)) => Self::Integer(lhs >> rhs),
-            (lhs, rhs) => Self::Integer(lhs.truthy() as i64 >> rhs.truthy() as i64),
+
+    // Applies an operation to two integers, falling back to big integers if
+    // it overflows. None if either side is not an integer
+    fn arithmetic(
+        &self,
+        rhs: &Self,
+        word: fn(i64, i64) -> Option<i64>,
+        big: fn(BigInt, BigInt) -> BigInt,
+    ) -> Option<Self> {
+        if let (Self::Integer(lhs), Self::Integer(rhs)) = (self, rhs) {
+            if let Some(result) = word(*lhs, *rhs) {
+                return Some(Self::Integer(result));
+            }
         }
+        Some(Self::from_big(big(self.big()?, rhs.big()?)))
+    }
+
+    pub fn and(&self, rhs: &Self) -> Self {
+        self.arithmetic(rhs, |lhs, rhs| Some(lhs & rhs), |lhs, rhs| lhs & rhs)
+            .unwrap_or_else(|| Self::Integer((self.truthy() && rhs.truthy()) as i64))
+    }
+    pub fn or(&self, rhs: &Self) -> Self {
+        self.arithmetic(rhs, |lhs, rhs| Some(lhs | rhs), |lhs, rhs| lhs | rhs)
+            .unwrap_or_else(|| Self::Integer((self.truthy() || rhs.truthy()) as i64))
+    }
+    pub fn xor(&self, rhs: &Self) -> Self {
+        self.arithmetic(rhs, |lhs, rhs| Some(lhs ^ rhs), |lhs, rhs| lhs ^ rhs)
+            .unwrap_or_else(|| Self::Integer((self.truthy() ^ rhs.truthy()) as i64))
+    }
+    pub fn shr(&self, rhs: &Self) -> Self {
+        self.arithmetic(
+            rhs,
+            |lhs, rhs| lhs.checked_shr(u32::try_from(rhs).ok()?),
+            |lhs, rhs| lhs >> shift_amount(&rhs),
+        )
+        .unwrap_or_else(|| Self::Integer(self.truthy() as i64 >> rhs.truthy() as i64))
     }
     pub fn shl(&self, rhs: &Self) -> Self {
+        self.arithmetic(
+            rhs,
+            // Overflowed if shifting back does not give the original value
+            |lhs, rhs| {
+                lhs.checked_shl(u32::try_from(rhs).ok()?)
+                    .filter(|shifted| shifted >> rhs == lhs)
+            },
+            |lhs, rhs| lhs << shift_amount(&rhs),
+        )
+        .unwrap_or_else(|| Self::Integer((self.truthy() as i64) << (rhs.truthy() as i64)))
+    }
+    /// `shl` on a machine word, the bits shifted out of it are lost
+    pub fn word_shl(&self, rhs: &Self) -> Self {
         match (self, rhs) {
-            (Self::Integer(lhs), Self::Integer(rhs)) => Self::Integer(lhs << rhs),
-            (lhs, rhs) => Self::Integer((lhs.truthy() as i64) << (rhs.truthy() as i64)),
+            (Self::Integer(lhs), Self::Integer(rhs)) if *rhs >= 0 => Self::Integer(
+                u32::try_from(*rhs).map_or(0, |rhs| lhs.checked_shl(rhs).unwrap_or(0)),
+            ),
+            _ => self.shl(rhs),
         }
     }
-    pub fn add(&self, rhs: &Self) -> Self {
+    /// `shr` on a machine word, shifting the sign in
+    pub fn word_shr(&self, rhs: &Self) -> Self {
         match (self, rhs) {
-            (Self::Integer(lhs), Self::Integer(rhs)) => Self::Integer(lhs + rhs),
-            (lhs, rhs) => Self::Integer((lhs.truthy() as i64) + (rhs.truthy() as i64)),
+            (Self::Integer(lhs), Self::Integer(rhs)) if *rhs >= 0 => {
+                let rhs = u32::try_from(*rhs).unwrap_or(u32::MAX).min(63);
+                Self::Integer(lhs >> rhs)
+            }
+            _ => self.shr(rhs),
         }
     }
+    pub fn add(&self, rhs: &Self) -> Self {
+        self.arithmetic(rhs, i64::checked_add, |lhs, rhs| lhs + rhs)
+            .unwrap_or_else(|| Self::Integer((self.truthy() as i64) + (rhs.truthy() as i64)))
+    }
     pub fn sub(&self, rhs: &Self) -> Self {
-        match (self, rhs) {
-            (Self::Integer(lhs), Self::Integer(rhs)) => Self::Integer(lhs - rhs),
-            (lhs, rhs) => Self::Integer((lhs.truthy() as i64) - (rhs.truthy() as i64)),
-        }
+        self.arithmetic(rhs, i64::checked_sub, |lhs, rhs| lhs - rhs)
+            .unwrap_or_else(|| Self::Integer((self.truthy() as i64) - (rhs.truthy() as i64)))
     }
     pub fn mul(&self, rhs: &Self) -> Self {
-        match (self, rhs) {
-            (Self::Integer(lhs), Self::Integer(rhs)) => Self::Integer(lhs * rhs),
-            (lhs, _) => Self::Integer(lhs.truthy() as i64),
-        }
+        self.arithmetic(rhs, i64::checked_mul, |lhs, rhs| lhs * rhs)
+            .unwrap_or_else(|| Self::Integer(self.truthy() as i64))
     }
     pub fn div(&self, rhs: &Self) -> Self {
-        match (self, rhs) {
-            (Self::Integer(lhs), Self::Integer(rhs)) => Self::Integer(lhs / rhs),
-            (lhs, _) => Self::Integer(lhs.truthy() as i64),
-        }
+        // Dividing by zero fails the checked division and panics in BigInt's
+        self.arithmetic(rhs, i64::checked_div, |lhs, rhs| lhs / rhs)
+            .unwrap_or_else(|| Self::Integer(self.truthy() as i64))
     }
     pub fn modulo(&self, rhs: &Self) -> Self {
-        match (self, rhs) {
-            (Self::Integer(lhs), Self::Integer(rhs)) => Self::Integer(lhs % rhs),
-            (lhs, rhs) => Self::Integer((lhs.truthy() as i64) % (rhs.truthy() as i64)),
-        }
+        self.arithmetic(rhs, i64::checked_rem, |lhs, rhs| lhs % rhs)
+            .unwrap_or_else(|| {
+                // A falsy divisor has no remainder, leave rejecting it to the caller
+                let rhs = rhs.truthy() as i64;
+                Self::Integer((self.truthy() as i64).checked_rem(rhs).unwrap_or(0))
+            })
     }
     pub fn not(&self) -> Self {
         Const::Integer((!self.truthy()) as i64)
@@ -110,34 +282,115 @@ impl Const {
     pub fn complement(&self) -> Self {
         match self {
             Self::Integer(i) => Self::Integer(!i),
+            Self::BigInteger(i) => Self::from_big(!i),
             i => Self::Integer(!i.truthy() as i64),
         }
     }
     pub fn negate(&self) -> Self {
         match self {
-            Self::Integer(i) => Self::Integer(-i),
+            Self::Integer(i) => i
+                .checked_neg()
+                .map(Self::Integer)
+                .unwrap_or_else(|| Self::from_big(-BigInt::from(*i))),
+            Self::BigInteger(i) => Self::from_big(-i),
             i => Self::Integer(-(i.truthy() as i64)),
         }
     }
     pub fn inc(&self) -> Self {
         match self {
-            Self::Integer(i) => Self::Integer(i + 1),
+            Self::Integer(_) | Self::BigInteger(_) => self.add(&Self::Integer(1)),
             i => Self::Integer(i.truthy() as i64 + 1),
         }
     }
     pub fn dec(&self) -> Self {
         match self {
-            Self::Integer(i) => Self::Integer(i - 1),
+            Self::Integer(_) | Self::BigInteger(_) => self.sub(&Self::Integer(1)),
             i => Self::Integer(i.truthy() as i64 - 1),
         }
     }
-    pub fn index(&self, idx: Const) -> Box<Expression> {
-        match (self, idx) {
-            (Self::Vector(v), Self::Integer(i)) => {
-                v.get(i as usize).expect("Index out of bounds").clone()
+    /// The word read as the IEEE double the `#` operators work on
+    pub fn float(&self) -> f64 {
+        match self {
+            Self::Integer(i) => f64::from_bits(*i as u64),
+            other => other.truthy() as i64 as f64,
+        }
+    }
+    pub fn from_float(f: f64) -> Self {
+        Self::Integer(f.to_bits() as i64)
+    }
+    pub fn fadd(&self, rhs: &Self) -> Self {
+        Self::from_float(self.float() + rhs.float())
+    }
+    pub fn fsub(&self, rhs: &Self) -> Self {
+        Self::from_float(self.float() - rhs.float())
+    }
+    pub fn fmul(&self, rhs: &Self) -> Self {
+        Self::from_float(self.float() * rhs.float())
+    }
+    pub fn fdiv(&self, rhs: &Self) -> Self {
+        Self::from_float(self.float() / rhs.float())
+    }
+    pub fn feq(&self, rhs: &Self) -> Self {
+        Self::Integer((self.float() == rhs.float()) as i64)
+    }
+    pub fn fne(&self, rhs: &Self) -> Self {
+        Self::Integer((self.float() != rhs.float()) as i64)
+    }
+    pub fn flt(&self, rhs: &Self) -> Self {
+        Self::Integer((self.float() < rhs.float()) as i64)
+    }
+    pub fn fgt(&self, rhs: &Self) -> Self {
+        Self::Integer((self.float() > rhs.float()) as i64)
+    }
+    pub fn fle(&self, rhs: &Self) -> Self {
+        Self::Integer((self.float() <= rhs.float()) as i64)
+    }
+    pub fn fge(&self, rhs: &Self) -> Self {
+        Self::Integer((self.float() >= rhs.float()) as i64)
+    }
+    pub fn fnegate(&self) -> Self {
+        Self::from_float(-self.float())
+    }
+}
+fn shift_amount(rhs: &BigInt) -> usize {
+    rhs.to_usize()
+        .unwrap_or_else(|| panic!("Cannot shift by {} bits", rhs))
+}
+
+// Integers compare by value whichever way they are stored, anything else by
+// kind and then contents
+impl PartialEq for Const {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+impl PartialOrd for Const {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        fn kind(c: &Const) -> u8 {
+            match c {
+                Const::Integer(_) | Const::BigInteger(_) => 0,
+                Const::String(_) => 1,
+                Const::Vector(_) => 2,
+                Const::HeapVector(_) => 3,
+                Const::Map(_) => 4,
+                Const::Ident(_) => 5,
+                Const::Error(_) => 6,
+                Const::Poison(..) => 7,
             }
-            (Self::Vector(_), _) => panic!("Attempt to index with non integer index"),
-            _ => panic!("Attempt to index a non vector object"),
+        }
+
+        match (self, other) {
+            (Self::Integer(lhs), Self::Integer(rhs)) => lhs.partial_cmp(rhs),
+            (Self::String(lhs), Self::String(rhs)) => lhs.partial_cmp(rhs),
+            (Self::Vector(lhs), Self::Vector(rhs)) => lhs.partial_cmp(rhs),
+            (Self::HeapVector(lhs), Self::HeapVector(rhs)) => lhs.partial_cmp(rhs),
+            (Self::Map(lhs), Self::Map(rhs)) => Rc::as_ptr(lhs).partial_cmp(&Rc::as_ptr(rhs)),
+            (Self::Ident(lhs), Self::Ident(rhs)) => lhs.partial_cmp(rhs),
+            (Self::Error(lhs), Self::Error(rhs)) => lhs.partial_cmp(rhs),
+            (lhs, rhs) => match (lhs.big(), rhs.big()) {
+                (Some(lhs), Some(rhs)) => lhs.partial_cmp(&rhs),
+                _ => kind(lhs).partial_cmp(&kind(rhs)),
+            },
         }
     }
 }
@@ -145,8 +398,117 @@ impl Display for Const {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         match self {
             Self::Integer(i) => write!(f, "{}", i),
-            Self::String(s) => write!(f, "{}", s.trim_matches('"')),
-            _ => write!(f, ""),
+            Self::BigInteger(i) => write!(f, "{}", i),
+            Self::String(s) => write!(f, "{}", terminated(s)),
+            _ => write!(f, "{}", self.render(None)),
+        }
+    }
+}
+
+impl Const {
+    /// Writes the value out in full, strings quoted and vectors and maps with
+    /// their elements. Vectors from `getvec` are only followed when `heap`
+    /// is given, and one that contains itself is written as `[...]` where it
+    /// repeats.
+    pub fn render(&self, heap: Option<&Heap>) -> String {
+        let mut renderer = Renderer {
+            heap,
+            vectors: vec![],
+            maps: vec![],
+            out: String::new(),
+        };
+        renderer.write(self);
+        renderer.out
+    }
+
+    /// What kind of value this is, as `dump` names it
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Integer(_) => "integer",
+            Self::BigInteger(_) => "big integer",
+            Self::String(_) => "string",
+            Self::Vector(_) => "vector literal",
+            Self::HeapVector(_) => "vector",
+            Self::Map(_) => "map",
+            Self::Ident(_) => "name",
+            Self::Error(_) => "error",
+            Self::Poison(..) => "unassigned",
+        }
+    }
+}
+
+// Tracks the vectors and maps being written so a cycle stops at the first
+// repeat, aliases that are not cycles are written out every time
+struct Renderer<'a> {
+    heap: Option<&'a Heap>,
+    vectors: Vec<HeapRef>,
+    maps: Vec<*const RefCell<HashMap<MapKey, Const>>>,
+    out: String,
+}
+impl Renderer<'_> {
+    fn write(&mut self, value: &Const) {
+        match value {
+            Const::String(s) => self.out.push_str(&format!("\"{}\"", escape(terminated(s)))),
+            Const::Vector(items) => {
+                self.out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    match item.expect_const() {
+                        Some(c) => self.write(&c),
+                        None => self.out.push_str(&item.to_string()),
+                    }
+                }
+                self.out.push(']');
+            }
+            Const::HeapVector(vector) => {
+                let words = match self.heap.map(|heap| heap.words(*vector)) {
+                    Some(Ok(words)) => words,
+                    Some(Err(e)) => return self.out.push_str(&format!("<{}>", e)),
+                    None => return self.out.push_str(&format!("<{}>", vector)),
+                };
+                if self.vectors.contains(vector) {
+                    return self.out.push_str("[...]");
+                }
+                self.vectors.push(*vector);
+                self.out.push('[');
+                for (i, word) in words.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.write(word);
+                }
+                self.out.push(']');
+                self.vectors.pop();
+            }
+            Const::Map(map) => {
+                if self.maps.contains(&Rc::as_ptr(map)) {
+                    return self.out.push_str("{...}");
+                }
+                self.maps.push(Rc::as_ptr(map));
+                let mut entries: Vec<(Const, Const)> = map
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| (key.to_const(), value.clone()))
+                    .collect();
+                entries.sort_by(|lhs, rhs| lhs.0.partial_cmp(&rhs.0).unwrap());
+                self.out.push('{');
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.write(key);
+                    self.out.push_str(": ");
+                    self.write(value);
+                }
+                self.out.push('}');
+                self.maps.pop();
+            }
+            Const::Ident(ident) => self.out.push_str(&ident.to_string()),
+            Const::Error(message) => self.out.push_str(&format!("<error: {}>", message)),
+            Const::Poison(ident, _) => self.out.push_str(&format!("<unassigned {}>", ident)),
+            Const::Integer(_) | Const::BigInteger(_) => self.out.push_str(&value.to_string()),
         }
     }
 }
@@ -155,208 +517,669 @@ pub enum Expression {
     Assign {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     AssignOr {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     AssignXor {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     AssignAnd {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     AssignShiftLeft {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     AssignShiftRight {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     AssignAdd {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     AssignSubtract {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     AssignMultiply {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     AssignDivide {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     AssignModulo {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     Ternary {
         condition: Box<Expression>,
         yes: Box<Expression>,
         no: Box<Expression>,
+        span: Span,
     },
     Equal {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     NotEqual {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     Less {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     More {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     LessEqual {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     MoreEqual {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     Or {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     Xor {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     And {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     ShiftLeft {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     ShiftRight {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     Add {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     Subtract {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     Multiply {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     Divide {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
     },
     Modulo {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        span: Span,
+    },
+    FloatAdd {
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+        span: Span,
+    },
+    FloatSubtract {
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+        span: Span,
+    },
+    FloatMultiply {
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+        span: Span,
+    },
+    FloatDivide {
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+        span: Span,
+    },
+    FloatEqual {
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+        span: Span,
+    },
+    FloatNotEqual {
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+        span: Span,
+    },
+    FloatLess {
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+        span: Span,
+    },
+    FloatMore {
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+        span: Span,
+    },
+    FloatLessEqual {
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+        span: Span,
+    },
+    FloatMoreEqual {
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+        span: Span,
     },
     Not {
         rhs: Box<Expression>,
+        span: Span,
     },
     Complement {
         rhs: Box<Expression>,
+        span: Span,
     },
     PreIncrement {
         rhs: Box<Expression>,
+        span: Span,
     },
     PreDecrement {
         rhs: Box<Expression>,
+        span: Span,
     },
     UnaryPlus {
         rhs: Box<Expression>,
+        span: Span,
     },
     UnaryMinus {
         rhs: Box<Expression>,
+        span: Span,
+    },
+    FloatNegate {
+        rhs: Box<Expression>,
+        span: Span,
     },
     PostIncrement {
         lhs: Box<Expression>,
+        span: Span,
     },
     PostDecrement {
         lhs: Box<Expression>,
+        span: Span,
     },
     VectorIndex {
         vector: Box<Expression>,
         index: Box<Expression>,
+        span: Span,
+    },
+    // A value, either a literal in the source or one worked out while
+    // running, which has the empty span of a made-up node
+    Constant {
+        value: Const,
+        span: Span,
+    },
+    Identifier {
+        ident: Identifier,
+        span: Span,
     },
-    Constant(Const),
-    Identifier(Identifier),
     FunctionCall {
         ident: Identifier,
         args: Vec<Box<Expression>>,
+        span: Span,
     },
 }
 impl Expression {
+    /// The source the node was parsed from
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::Assign { span, .. }
+            | Self::AssignOr { span, .. }
+            | Self::AssignXor { span, .. }
+            | Self::AssignAnd { span, .. }
+            | Self::AssignShiftLeft { span, .. }
+            | Self::AssignShiftRight { span, .. }
+            | Self::AssignAdd { span, .. }
+            | Self::AssignSubtract { span, .. }
+            | Self::AssignMultiply { span, .. }
+            | Self::AssignDivide { span, .. }
+            | Self::AssignModulo { span, .. }
+            | Self::Ternary { span, .. }
+            | Self::Equal { span, .. }
+            | Self::NotEqual { span, .. }
+            | Self::Less { span, .. }
+            | Self::More { span, .. }
+            | Self::LessEqual { span, .. }
+            | Self::MoreEqual { span, .. }
+            | Self::Or { span, .. }
+            | Self::Xor { span, .. }
+            | Self::And { span, .. }
+            | Self::ShiftLeft { span, .. }
+            | Self::ShiftRight { span, .. }
+            | Self::Add { span, .. }
+            | Self::Subtract { span, .. }
+            | Self::Multiply { span, .. }
+            | Self::Divide { span, .. }
+            | Self::Modulo { span, .. }
+            | Self::FloatAdd { span, .. }
+            | Self::FloatSubtract { span, .. }
+            | Self::FloatMultiply { span, .. }
+            | Self::FloatDivide { span, .. }
+            | Self::FloatEqual { span, .. }
+            | Self::FloatNotEqual { span, .. }
+            | Self::FloatLess { span, .. }
+            | Self::FloatMore { span, .. }
+            | Self::FloatLessEqual { span, .. }
+            | Self::FloatMoreEqual { span, .. }
+            | Self::Not { span, .. }
+            | Self::Complement { span, .. }
+            | Self::PreIncrement { span, .. }
+            | Self::PreDecrement { span, .. }
+            | Self::UnaryPlus { span, .. }
+            | Self::UnaryMinus { span, .. }
+            | Self::FloatNegate { span, .. }
+            | Self::PostIncrement { span, .. }
+            | Self::PostDecrement { span, .. }
+            | Self::VectorIndex { span, .. }
+            | Self::Constant { span, .. }
+            | Self::Identifier { span, .. }
+            | Self::FunctionCall { span, .. } => Some(*span),
+        }
+    }
     pub fn expect_const(&self) -> Option<Const> {
         match self {
-            Self::Constant(c) => Some(c.clone()),
+            Self::Constant { value, .. } => Some(value.clone()),
             _ => None,
         }
     }
     pub fn expect_ident(&self) -> Option<Identifier> {
         match self {
-            Self::Identifier(i) => Some(i.clone()),
+            Self::Identifier { ident, .. } => Some(ident.clone()),
             _ => None,
         }
     }
+
+    /// The expressions directly inside this one, in the order they are in
+    /// the source
+    pub fn operands(&self) -> Vec<&Expression> {
+        if let Some((lhs, _, rhs)) = self.binary_operator() {
+            return vec![lhs, rhs];
+        }
+        match self {
+            Self::Ternary {
+                condition, yes, no, ..
+            } => vec![condition, yes, no],
+            Self::Not { rhs, .. }
+            | Self::Complement { rhs, .. }
+            | Self::PreIncrement { rhs, .. }
+            | Self::PreDecrement { rhs, .. }
+            | Self::UnaryPlus { rhs, .. }
+            | Self::UnaryMinus { rhs, .. }
+            | Self::FloatNegate { rhs, .. } => vec![rhs],
+            Self::PostIncrement { lhs, .. } | Self::PostDecrement { lhs, .. } => vec![lhs],
+            Self::VectorIndex { vector, index, .. } => vec![vector, index],
+            Self::FunctionCall { args, .. }
+            | Self::Constant {
+                value: Const::Vector(args),
+                ..
+            } => args.iter().map(|arg| &**arg).collect(),
+            _ => vec![],
+        }
+    }
+
+    // How tightly the expression binds, higher binds tighter. Follows the
+    // levels in the grammar so printed expressions parse back the same way
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Assign { .. }
+            | Self::AssignOr { .. }
+            | Self::AssignXor { .. }
+            | Self::AssignAnd { .. }
+            | Self::AssignShiftLeft { .. }
+            | Self::AssignShiftRight { .. }
+            | Self::AssignAdd { .. }
+            | Self::AssignSubtract { .. }
+            | Self::AssignMultiply { .. }
+            | Self::AssignDivide { .. }
+            | Self::AssignModulo { .. } => 1,
+            Self::Ternary { .. } => 2,
+            Self::Equal { .. }
+            | Self::NotEqual { .. }
+            | Self::FloatEqual { .. }
+            | Self::FloatNotEqual { .. } => 3,
+            Self::Less { .. }
+            | Self::More { .. }
+            | Self::LessEqual { .. }
+            | Self::MoreEqual { .. }
+            | Self::FloatLess { .. }
+            | Self::FloatMore { .. }
+            | Self::FloatLessEqual { .. }
+            | Self::FloatMoreEqual { .. } => 4,
+            Self::Or { .. } => 5,
+            Self::Xor { .. } => 6,
+            Self::And { .. } => 7,
+            Self::ShiftLeft { .. } | Self::ShiftRight { .. } => 8,
+            Self::Add { .. }
+            | Self::Subtract { .. }
+            | Self::FloatAdd { .. }
+            | Self::FloatSubtract { .. } => 9,
+            Self::Multiply { .. }
+            | Self::Divide { .. }
+            | Self::Modulo { .. }
+            | Self::FloatMultiply { .. }
+            | Self::FloatDivide { .. } => 10,
+            Self::Not { .. }
+            | Self::Complement { .. }
+            | Self::PreIncrement { .. }
+            | Self::PreDecrement { .. }
+            | Self::UnaryPlus { .. }
+            | Self::UnaryMinus { .. }
+            | Self::FloatNegate { .. } => 11,
+            _ => 12,
+        }
+    }
+
+    fn binary_operator(&self) -> Option<(&Expression, &'static str, &Expression)> {
+        let (lhs, operator, rhs) = match self {
+            Self::Assign { lhs, rhs, .. } => (lhs, "=", rhs),
+            Self::AssignOr { lhs, rhs, .. } => (lhs, "=|", rhs),
+            Self::AssignXor { lhs, rhs, .. } => (lhs, "=^", rhs),
+            Self::AssignAnd { lhs, rhs, .. } => (lhs, "=&", rhs),
+            Self::AssignShiftLeft { lhs, rhs, .. } => (lhs, "=<<", rhs),
+            Self::AssignShiftRight { lhs, rhs, .. } => (lhs, "=>>", rhs),
+            Self::AssignAdd { lhs, rhs, .. } => (lhs, "=+", rhs),
+            Self::AssignSubtract { lhs, rhs, .. } => (lhs, "=-", rhs),
+            Self::AssignMultiply { lhs, rhs, .. } => (lhs, "=*", rhs),
+            Self::AssignDivide { lhs, rhs, .. } => (lhs, "=/", rhs),
+            Self::AssignModulo { lhs, rhs, .. } => (lhs, "=%", rhs),
+            Self::Equal { lhs, rhs, .. } => (lhs, "==", rhs),
+            Self::NotEqual { lhs, rhs, .. } => (lhs, "!=", rhs),
+            Self::Less { lhs, rhs, .. } => (lhs, "<", rhs),
+            Self::More { lhs, rhs, .. } => (lhs, ">", rhs),
+            Self::LessEqual { lhs, rhs, .. } => (lhs, "<=", rhs),
+            Self::MoreEqual { lhs, rhs, .. } => (lhs, ">=", rhs),
+            Self::Or { lhs, rhs, .. } => (lhs, "|", rhs),
+            Self::Xor { lhs, rhs, .. } => (lhs, "^", rhs),
+            Self::And { lhs, rhs, .. } => (lhs, "&", rhs),
+            Self::ShiftLeft { lhs, rhs, .. } => (lhs, "<<", rhs),
+            Self::ShiftRight { lhs, rhs, .. } => (lhs, ">>", rhs),
+            Self::Add { lhs, rhs, .. } => (lhs, "+", rhs),
+            Self::Subtract { lhs, rhs, .. } => (lhs, "-", rhs),
+            Self::Multiply { lhs, rhs, .. } => (lhs, "*", rhs),
+            Self::Divide { lhs, rhs, .. } => (lhs, "/", rhs),
+            Self::Modulo { lhs, rhs, .. } => (lhs, "%", rhs),
+            Self::FloatAdd { lhs, rhs, .. } => (lhs, "#+", rhs),
+            Self::FloatSubtract { lhs, rhs, .. } => (lhs, "#-", rhs),
+            Self::FloatMultiply { lhs, rhs, .. } => (lhs, "#*", rhs),
+            Self::FloatDivide { lhs, rhs, .. } => (lhs, "#/", rhs),
+            Self::FloatEqual { lhs, rhs, .. } => (lhs, "#==", rhs),
+            Self::FloatNotEqual { lhs, rhs, .. } => (lhs, "#!=", rhs),
+            Self::FloatLess { lhs, rhs, .. } => (lhs, "#<", rhs),
+            Self::FloatMore { lhs, rhs, .. } => (lhs, "#>", rhs),
+            Self::FloatLessEqual { lhs, rhs, .. } => (lhs, "#<=", rhs),
+            Self::FloatMoreEqual { lhs, rhs, .. } => (lhs, "#>=", rhs),
+            _ => return None,
+        };
+        Some((lhs, operator, rhs))
+    }
+
+    fn write_operand(&self, f: &mut Formatter, precedence: u8) -> std::fmt::Result {
+        if self.precedence() < precedence {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let precedence = self.precedence();
+        if let Some((lhs, operator, rhs)) = self.binary_operator() {
+            // Assignments group to the right, everything else to the left
+            let (lhs_precedence, rhs_precedence) = if precedence == 1 {
+                (2, 1)
+            } else {
+                (precedence, precedence + 1)
+            };
+            lhs.write_operand(f, lhs_precedence)?;
+            write!(f, " {} ", operator)?;
+            return rhs.write_operand(f, rhs_precedence);
+        }
+
+        match self {
+            Self::Ternary {
+                condition, yes, no, ..
+            } => {
+                condition.write_operand(f, 2)?;
+                write!(f, " ? ")?;
+                yes.write_operand(f, 3)?;
+                write!(f, " : ")?;
+                no.write_operand(f, 3)
+            }
+            Self::Not { rhs, .. } => write!(f, "!").and_then(|_| rhs.write_operand(f, 11)),
+            Self::Complement { rhs, .. } => write!(f, "~").and_then(|_| rhs.write_operand(f, 11)),
+            Self::PreIncrement { rhs, .. } => {
+                write!(f, "++").and_then(|_| rhs.write_operand(f, 11))
+            }
+            Self::PreDecrement { rhs, .. } => {
+                write!(f, "--").and_then(|_| rhs.write_operand(f, 11))
+            }
+            Self::UnaryPlus { rhs, .. } => write!(f, "+").and_then(|_| rhs.write_operand(f, 11)),
+            Self::UnaryMinus { rhs, .. } => write!(f, "-").and_then(|_| rhs.write_operand(f, 11)),
+            Self::FloatNegate { rhs, .. } => write!(f, "#-").and_then(|_| rhs.write_operand(f, 11)),
+            Self::PostIncrement { lhs, .. } => {
+                lhs.write_operand(f, 12).and_then(|_| write!(f, "++"))
+            }
+            Self::PostDecrement { lhs, .. } => {
+                lhs.write_operand(f, 12).and_then(|_| write!(f, "--"))
+            }
+            Self::VectorIndex { vector, index, .. } => {
+                vector.write_operand(f, 12)?;
+                write!(f, "[{}]", index)
+            }
+            Self::Constant {
+                value: Const::String(s),
+                ..
+            } => write!(f, "\"{}\"", escape(s)),
+            Self::Constant {
+                value: Const::Vector(items),
+                ..
+            } => {
+                let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Self::Constant {
+                value: Const::Ident(ident),
+                ..
+            }
+            | Self::Identifier { ident, .. } => {
+                write!(f, "{}", ident)
+            }
+            Self::Constant { value, .. } => write!(f, "{}", value),
+            Self::FunctionCall { ident, args, .. } => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "{}({})", ident, args.join(", "))
+            }
+            _ => unreachable!("binary operators are written above"),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct CaseStatement {
     pub case: Const,
     pub body: Option<Vec<Statement>>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub enum Statement {
-    Compound(Vec<Statement>),
-    Return(Expression),
-    Expression(Expression),
+    Compound(Vec<Statement>, Span),
+    Return(Expression, Span),
+    Expression(Expression, Span),
     Declaration {
         scope: VariableScope,
         idents: Vec<Identifier>,
+        span: Span,
     },
     Conditional {
         condition: Expression,
         body: Box<Statement>,
         e: Option<Box<Statement>>,
+        span: Span,
     },
     Loop {
         condition: Expression,
         body: Box<Statement>,
+        span: Span,
     },
     // TODO: Default
     Switch {
         switching_on: Expression,
         cases: Vec<CaseStatement>,
+        span: Span,
     },
-    Label(Identifier),
-    Goto(Identifier),
+    Label(Identifier, Span),
+    Goto(Identifier, Span),
     FunctionDefinition {
         ident: Identifier,
         args: Vec<Identifier>,
         body: Box<Statement>,
+        span: Span,
     },
     GlobalDefinition {
         ident: Identifier,
         initial_value: Const,
+        span: Span,
     },
 
-    Break,
+    Break(Span),
+
+    // Source that did not parse, skipped so the rest could be
+    Error(Span),
 
+    // The body of a builtin, never parsed so it has no span
     Null,
 }
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Compound(_, span)
+            | Self::Return(_, span)
+            | Self::Expression(_, span)
+            | Self::Declaration { span, .. }
+            | Self::Conditional { span, .. }
+            | Self::Loop { span, .. }
+            | Self::Switch { span, .. }
+            | Self::Label(_, span)
+            | Self::Goto(_, span)
+            | Self::FunctionDefinition { span, .. }
+            | Self::GlobalDefinition { span, .. }
+            | Self::Break(span)
+            | Self::Error(span) => *span,
+            Self::Null => Span::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    fn big(digits: &str) -> BigInt {
+        digits.parse().unwrap()
+    }
+
+    #[test]
+    fn big_integers_that_fit_in_a_word_become_words() {
+        assert_eq!(Const::from_big(BigInt::from(5)), Const::Integer(5));
+        assert!(matches!(
+            Const::from_big(BigInt::from(i64::MIN)),
+            Const::Integer(i64::MIN)
+        ));
+        assert!(matches!(
+            Const::from_big(big("9223372036854775808")),
+            Const::BigInteger(_)
+        ));
+        let back = Const::from_big(big("9223372036854775808")).sub(&Const::Integer(1));
+        assert!(matches!(back, Const::Integer(i64::MAX)));
+    }
+
+    #[test]
+    fn overflow_grows_or_wraps() {
+        let max = Const::Integer(i64::MAX);
+        let grown = max.add(&Const::Integer(1));
+        assert_eq!(grown, Const::BigInteger(big("9223372036854775808")));
+        assert_eq!(grown.clone().wrapped(64), Const::Integer(i64::MIN));
+        assert_eq!(
+            Const::from_big(big("18446744073709551617")).wrapped(64),
+            Const::Integer(1)
+        );
+        assert_eq!(Const::Integer(32768).wrapped(16), Const::Integer(-32768));
+        assert_eq!(Const::Integer(-1).wrapped(16), Const::Integer(-1));
+        assert_eq!(grown.wrapped(16), Const::Integer(0));
+    }
+
+    #[test]
+    fn word_shifts_lose_the_bits_shifted_out() {
+        let shl = |lhs: i64, rhs: i64| Const::Integer(lhs).word_shl(&Const::Integer(rhs));
+        let shr = |lhs: i64, rhs: i64| Const::Integer(lhs).word_shr(&Const::Integer(rhs));
+        assert_eq!(shl(5, 1), Const::Integer(10));
+        assert_eq!(shl(1, 63), Const::Integer(i64::MIN));
+        assert_eq!(shl(1, 64), Const::Integer(0));
+        assert_eq!(shl(1, 4_000_000_000_000), Const::Integer(0));
+        assert_eq!(shr(-8, 1), Const::Integer(-4));
+        assert_eq!(shr(-8, 4_000_000_000_000), Const::Integer(-1));
+        assert_eq!(shr(8, 100), Const::Integer(0));
+    }
+
+    #[test]
+    fn shifts_never_build_huge_integers() {
+        let source = "main() { return 1 << 4000000000000; }";
+        assert_eq!(Interpreter::new().interpret_string(source).unwrap(), 0);
+        let e = Interpreter::new()
+            .with_big_integers(true)
+            .interpret_string(source)
+            .unwrap_err();
+        assert_eq!(e.kind, crate::interpreter::ErrorKind::InvalidOperand);
+        let source = "main() { return (1 << 100) >> 98; }";
+        let result = Interpreter::new()
+            .with_big_integers(true)
+            .interpret_string(source);
+        assert_eq!(result.unwrap(), 4);
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    fmt::Debug,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// The parts of the outside world a program can observe through `time`,
+/// `clock`, `rand` and `srand`.
+pub trait Environment: Debug {
+    /// Seconds since the UNIX epoch
+    fn time(&mut self) -> i64;
+    /// Milliseconds since the program started
+    fn clock(&mut self) -> i64;
+    /// The next pseudo random number, between 0 and `RAND_MAX`
+    fn rand(&mut self) -> i64;
+    fn srand(&mut self, seed: i64);
+}
+
+pub const RAND_MAX: i64 = 0x7fff_ffff;
+
+/// A splitmix64 generator, small and the same on every platform, so a given
+/// seed always produces the same numbers.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+impl Rng {
+    pub fn new(seed: i64) -> Self {
+        Self { state: seed as u64 }
+    }
+
+    pub fn generate(&mut self) -> i64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        ((z ^ (z >> 31)) >> 33) as i64 & RAND_MAX
+    }
+}
+
+/// The real clock, with the generator seeded with 1 until the program calls
+/// `srand`, like C's `rand`.
+#[derive(Debug)]
+pub struct SystemEnvironment {
+    started: Instant,
+    rng: Rng,
+}
+impl SystemEnvironment {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            rng: Rng::new(1),
+        }
+    }
+}
+impl Default for SystemEnvironment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Environment for SystemEnvironment {
+    fn time(&mut self) -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or(0)
+    }
+    fn clock(&mut self) -> i64 {
+        self.started.elapsed().as_millis() as i64
+    }
+    fn rand(&mut self) -> i64 {
+        self.rng.generate()
+    }
+    fn srand(&mut self, seed: i64) {
+        self.rng = Rng::new(seed);
+    }
+}
+
+/// A clock that only moves when told to, for runs that have to produce the
+/// same output every time.
+#[derive(Debug)]
+pub struct PinnedEnvironment {
+    pub time: i64,
+    pub clock: i64,
+    rng: Rng,
+}
+impl PinnedEnvironment {
+    pub fn new(time: i64, seed: i64) -> Self {
+        Self {
+            time,
+            clock: 0,
+            rng: Rng::new(seed),
+        }
+    }
+}
+impl Environment for PinnedEnvironment {
+    fn time(&mut self) -> i64 {
+        self.time
+    }
+    fn clock(&mut self) -> i64 {
+        self.clock
+    }
+    fn rand(&mut self) -> i64 {
+        self.rng.generate()
+    }
+    fn srand(&mut self, seed: i64) {
+        self.rng = Rng::new(seed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    fn run(environment: PinnedEnvironment, source: &str) -> i64 {
+        Interpreter::new()
+            .with_environment(environment)
+            .interpret_string(source)
+            .unwrap()
+    }
+
+    #[test]
+    fn a_seed_always_gives_the_same_numbers() {
+        let (mut a, mut b) = (Rng::new(42), Rng::new(42));
+        let numbers: Vec<_> = (0..100).map(|_| a.generate()).collect();
+        assert_eq!(numbers, (0..100).map(|_| b.generate()).collect::<Vec<_>>());
+        assert!(numbers.iter().all(|n| (0..=RAND_MAX).contains(n)));
+        assert_ne!(Rng::new(1).generate(), Rng::new(2).generate());
+    }
+
+    #[test]
+    fn pinned_time_and_clock_do_not_move() {
+        let mut environment = PinnedEnvironment::new(1_000_000, 1);
+        assert_eq!(
+            (environment.time(), environment.time()),
+            (1_000_000, 1_000_000)
+        );
+        assert_eq!(environment.clock(), 0);
+        environment.clock = 250;
+        assert_eq!(environment.clock(), 250);
+    }
+
+    #[test]
+    fn programs_see_the_pinned_environment() {
+        let environment = || PinnedEnvironment::new(86_400, 7);
+        assert_eq!(run(environment(), "main() { return time(); }"), 86_400);
+        assert_eq!(run(environment(), "main() { return clock(); }"), 0);
+        assert_eq!(
+            run(environment(), "main() { return rand(); }"),
+            Rng::new(7).generate()
+        );
+    }
+
+    #[test]
+    fn srand_restarts_the_sequence() {
+        let source =
+            "main() { auto a; srand(5); a = rand(); rand(); srand(5); return a == rand(); }";
+        assert_eq!(run(PinnedEnvironment::new(0, 1), source), 1);
+    }
+}
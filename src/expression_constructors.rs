@@ -1,124 +1,232 @@
-use crate::ast::{Const, Expression, Identifier};
+use crate::{
+    ast::{Const, Expression, Identifier},
+    source::Span,
+};
+
+// The span covering every operand that has one, so a node built around parsed
+// operands still points at them
+fn covering<'a>(operands: impl IntoIterator<Item = &'a Box<Expression>>) -> Span {
+    operands
+        .into_iter()
+        .filter_map(|operand| operand.span())
+        .fold(Span::default(), Span::to)
+}
 
 impl Expression {
     pub fn assign(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::Assign { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::Assign { lhs, rhs, span }
     }
     pub fn assign_or(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::AssignOr { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::AssignOr { lhs, rhs, span }
     }
     pub fn assign_xor(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::AssignXor { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::AssignXor { lhs, rhs, span }
     }
     pub fn assign_and(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::AssignAnd { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::AssignAnd { lhs, rhs, span }
     }
     pub fn assign_shift_left(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::AssignShiftLeft { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::AssignShiftLeft { lhs, rhs, span }
     }
     pub fn assign_shift_right(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::AssignShiftRight { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::AssignShiftRight { lhs, rhs, span }
     }
     pub fn assign_add(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::AssignAdd { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::AssignAdd { lhs, rhs, span }
     }
     pub fn assign_subtract(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::AssignSubtract { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::AssignSubtract { lhs, rhs, span }
     }
     pub fn assign_multiply(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::AssignMultiply { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::AssignMultiply { lhs, rhs, span }
     }
     pub fn assign_divide(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::AssignDivide { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::AssignDivide { lhs, rhs, span }
     }
     pub fn assign_modulo(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::AssignModulo { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::AssignModulo { lhs, rhs, span }
     }
     pub fn ternary(condition: Box<Expression>, yes: Box<Expression>, no: Box<Expression>) -> Self {
-        Self::Ternary { condition, yes, no }
+        let span = covering([&condition, &yes, &no]);
+        Self::Ternary {
+            condition,
+            yes,
+            no,
+            span,
+        }
     }
     pub fn equal(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::Equal { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::Equal { lhs, rhs, span }
     }
     pub fn not_equal(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::NotEqual { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::NotEqual { lhs, rhs, span }
     }
     pub fn less(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::Less { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::Less { lhs, rhs, span }
     }
     pub fn more(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::More { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::More { lhs, rhs, span }
     }
     pub fn less_equal(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::LessEqual { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::LessEqual { lhs, rhs, span }
     }
     pub fn more_equal(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::MoreEqual { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::MoreEqual { lhs, rhs, span }
     }
     pub fn or(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::Or { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::Or { lhs, rhs, span }
     }
     pub fn xor(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::Xor { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::Xor { lhs, rhs, span }
     }
     pub fn and(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::And { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::And { lhs, rhs, span }
     }
     pub fn shift_left(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::ShiftLeft { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::ShiftLeft { lhs, rhs, span }
     }
     pub fn shift_right(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::ShiftRight { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::ShiftRight { lhs, rhs, span }
     }
     pub fn add(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::Add { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::Add { lhs, rhs, span }
     }
     pub fn subtract(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::Subtract { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::Subtract { lhs, rhs, span }
     }
     pub fn multiply(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::Multiply { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::Multiply { lhs, rhs, span }
     }
     pub fn divide(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::Divide { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::Divide { lhs, rhs, span }
     }
     pub fn modulo(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
-        Self::Modulo { lhs, rhs }
+        let span = covering([&lhs, &rhs]);
+        Self::Modulo { lhs, rhs, span }
+    }
+    pub fn float_add(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
+        let span = covering([&lhs, &rhs]);
+        Self::FloatAdd { lhs, rhs, span }
+    }
+    pub fn float_subtract(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
+        let span = covering([&lhs, &rhs]);
+        Self::FloatSubtract { lhs, rhs, span }
+    }
+    pub fn float_multiply(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
+        let span = covering([&lhs, &rhs]);
+        Self::FloatMultiply { lhs, rhs, span }
+    }
+    pub fn float_divide(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
+        let span = covering([&lhs, &rhs]);
+        Self::FloatDivide { lhs, rhs, span }
+    }
+    pub fn float_equal(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
+        let span = covering([&lhs, &rhs]);
+        Self::FloatEqual { lhs, rhs, span }
+    }
+    pub fn float_not_equal(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
+        let span = covering([&lhs, &rhs]);
+        Self::FloatNotEqual { lhs, rhs, span }
+    }
+    pub fn float_less(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
+        let span = covering([&lhs, &rhs]);
+        Self::FloatLess { lhs, rhs, span }
+    }
+    pub fn float_more(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
+        let span = covering([&lhs, &rhs]);
+        Self::FloatMore { lhs, rhs, span }
+    }
+    pub fn float_less_equal(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
+        let span = covering([&lhs, &rhs]);
+        Self::FloatLessEqual { lhs, rhs, span }
+    }
+    pub fn float_more_equal(lhs: Box<Expression>, rhs: Box<Expression>) -> Self {
+        let span = covering([&lhs, &rhs]);
+        Self::FloatMoreEqual { lhs, rhs, span }
     }
     pub fn not(rhs: Box<Expression>) -> Self {
-        Self::Not { rhs }
+        let span = covering([&rhs]);
+        Self::Not { rhs, span }
     }
     pub fn complement(rhs: Box<Expression>) -> Self {
-        Self::Complement { rhs }
+        let span = covering([&rhs]);
+        Self::Complement { rhs, span }
     }
     pub fn pre_increment(rhs: Box<Expression>) -> Self {
-        Self::PreIncrement { rhs }
+        let span = covering([&rhs]);
+        Self::PreIncrement { rhs, span }
     }
     pub fn pre_decrement(rhs: Box<Expression>) -> Self {
-        Self::PreDecrement { rhs }
+        let span = covering([&rhs]);
+        Self::PreDecrement { rhs, span }
     }
     pub fn unary_plus(rhs: Box<Expression>) -> Self {
-        Self::UnaryPlus { rhs }
+        let span = covering([&rhs]);
+        Self::UnaryPlus { rhs, span }
     }
     pub fn unary_minus(rhs: Box<Expression>) -> Self {
-        Self::UnaryMinus { rhs }
+        let span = covering([&rhs]);
+        Self::UnaryMinus { rhs, span }
+    }
+    pub fn float_negate(rhs: Box<Expression>) -> Self {
+        let span = covering([&rhs]);
+        Self::FloatNegate { rhs, span }
     }
     pub fn post_increment(lhs: Box<Expression>) -> Self {
-        Self::PostIncrement { lhs }
+        let span = covering([&lhs]);
+        Self::PostIncrement { lhs, span }
     }
     pub fn post_decrement(lhs: Box<Expression>) -> Self {
-        Self::PostDecrement { lhs }
+        let span = covering([&lhs]);
+        Self::PostDecrement { lhs, span }
     }
     pub fn vector_index(vector: Box<Expression>, index: Box<Expression>) -> Self {
-        Self::VectorIndex { vector, index }
+        let span = covering([&vector, &index]);
+        Self::VectorIndex {
+            vector,
+            index,
+            span,
+        }
     }
     pub fn constant(item: Const) -> Self {
-        Self::Constant(item)
+        Self::Constant {
+            value: item,
+            span: Span::default(),
+        }
     }
     pub fn identifier(item: Identifier) -> Self {
-        Self::Identifier(item)
+        Self::Identifier {
+            ident: item,
+            span: Span::default(),
+        }
     }
-    pub fn function_call(ident: Identifier, args: Vec<Box<Expression>>) -> Self {
-        Self::FunctionCall { ident, args }
+    pub fn function_call(ident: Identifier, args: Vec<Box<Expression>>, span: Span) -> Self {
+        Self::FunctionCall { ident, args, span }
     }
 }
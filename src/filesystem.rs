@@ -0,0 +1,426 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    convert::TryFrom,
+    fmt::Debug,
+    fs::{self, OpenOptions},
+    io::{self, stderr, stdin, stdout, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+};
+
+pub trait FileHandle: Read + Write + Seek + Debug {}
+impl<T: Read + Write + Seek + Debug> FileHandle for T {}
+
+/// Where the file builtins (`open`, `creat`, ...) find their files.
+///
+/// Paths are always the string a B program passed, so an implementation is
+/// responsible for keeping them inside whatever it is willing to expose.
+pub trait FileSystem: Debug {
+    fn open(&mut self, path: &str, mode: OpenMode) -> io::Result<Box<dyn FileHandle>>;
+    /// Opens `path` for writing, creating it or truncating it if it exists
+    fn create(&mut self, path: &str) -> io::Result<Box<dyn FileHandle>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpenMode {
+    Read,
+    Write,
+    ReadWrite,
+}
+impl OpenMode {
+    /// The mode argument of UNIX `open`, 0 to read, 1 to write and 2 for both
+    pub fn from_word(mode: i64) -> Option<Self> {
+        match mode {
+            0 => Some(Self::Read),
+            1 => Some(Self::Write),
+            2 => Some(Self::ReadWrite),
+            _ => None,
+        }
+    }
+    fn readable(self) -> bool {
+        self != Self::Write
+    }
+    fn writable(self) -> bool {
+        self != Self::Read
+    }
+}
+
+/// Files under a directory on the host, anything resolving outside of it
+/// is refused.
+#[derive(Debug)]
+pub struct DirectoryFileSystem {
+    root: PathBuf,
+}
+impl DirectoryFileSystem {
+    pub fn new<P: AsRef<Path>>(root: P) -> io::Result<Self> {
+        Ok(Self {
+            root: fs::canonicalize(root)?,
+        })
+    }
+
+    fn resolve(&self, path: &str) -> io::Result<PathBuf> {
+        let escapes = Path::new(path)
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+        if escapes {
+            return Err(io::Error::new(
+                ErrorKind::PermissionDenied,
+                format!("{} is outside of {}", path, self.root.display()),
+            ));
+        }
+
+        // Symlinks can still point out of the root, so check where the file,
+        // or the directory it would be created in, really is. A symlink that
+        // points nowhere yet would be followed by creating the file
+        let path = self.root.join(path);
+        let dangling = !path.exists()
+            && fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.file_type().is_symlink());
+        if dangling {
+            return Err(self.outside(&path));
+        }
+        let resolved = if path.exists() {
+            fs::canonicalize(&path)?
+        } else {
+            path.parent()
+                .map(fs::canonicalize)
+                .transpose()?
+                .unwrap_or_default()
+        };
+        if resolved.starts_with(&self.root) {
+            Ok(path)
+        } else {
+            Err(self.outside(&path))
+        }
+    }
+
+    // The file could have been swapped for a symlink since it was resolved,
+    // so once it is open check again where it really is
+    fn opened(&self, path: &Path, file: fs::File) -> io::Result<Box<dyn FileHandle>> {
+        if fs::canonicalize(path)?.starts_with(&self.root) {
+            Ok(Box::new(file))
+        } else {
+            Err(self.outside(path))
+        }
+    }
+
+    fn outside(&self, path: &Path) -> io::Error {
+        io::Error::new(
+            ErrorKind::PermissionDenied,
+            format!("{} is outside of {}", path.display(), self.root.display()),
+        )
+    }
+}
+impl FileSystem for DirectoryFileSystem {
+    fn open(&mut self, path: &str, mode: OpenMode) -> io::Result<Box<dyn FileHandle>> {
+        let path = self.resolve(path)?;
+        let file = OpenOptions::new()
+            .read(mode.readable())
+            .write(mode.writable())
+            .open(&path)?;
+        self.opened(&path, file)
+    }
+
+    fn create(&mut self, path: &str) -> io::Result<Box<dyn FileHandle>> {
+        let path = self.resolve(path)?;
+        // A new file is created without following a symlink put in its place,
+        // only one that is already there is truncated
+        let file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                OpenOptions::new().write(true).truncate(true).open(&path)?
+            }
+            file => file?,
+        };
+        self.opened(&path, file)
+    }
+}
+
+/// Files that only exist in memory, for running programs without touching
+/// the host.
+#[derive(Debug, Default)]
+pub struct MemoryFileSystem {
+    files: HashMap<String, Rc<RefCell<Vec<u8>>>>,
+}
+impl MemoryFileSystem {
+    pub fn new() -> Self {
+        Self {
+            files: HashMap::new(),
+        }
+    }
+
+    pub fn with_file<S: ToString>(mut self, path: S, contents: &[u8]) -> Self {
+        self.files
+            .insert(path.to_string(), Rc::new(RefCell::new(contents.to_vec())));
+        self
+    }
+
+    pub fn contents(&self, path: &str) -> Option<Vec<u8>> {
+        self.files.get(path).map(|file| file.borrow().clone())
+    }
+}
+impl FileSystem for MemoryFileSystem {
+    fn open(&mut self, path: &str, _: OpenMode) -> io::Result<Box<dyn FileHandle>> {
+        match self.files.get(path) {
+            Some(data) => Ok(Box::new(MemoryFile {
+                data: data.clone(),
+                position: 0,
+            })),
+            None => Err(io::Error::new(
+                ErrorKind::NotFound,
+                format!("{} does not exist", path),
+            )),
+        }
+    }
+
+    fn create(&mut self, path: &str) -> io::Result<Box<dyn FileHandle>> {
+        let data = Rc::new(RefCell::new(vec![]));
+        self.files.insert(path.into(), data.clone());
+        Ok(Box::new(MemoryFile { data, position: 0 }))
+    }
+}
+
+#[derive(Debug)]
+struct MemoryFile {
+    data: Rc<RefCell<Vec<u8>>>,
+    position: usize,
+}
+impl Read for MemoryFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let data = self.data.borrow();
+        let available = data.get(self.position..).unwrap_or(&[]);
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+impl Write for MemoryFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut data = self.data.borrow_mut();
+        let end = self.position + buf.len();
+        if data.len() < end {
+            data.resize(end, 0);
+        }
+        data[self.position..end].copy_from_slice(buf);
+        self.position = end;
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+impl Seek for MemoryFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+            SeekFrom::End(offset) => self.data.borrow().len() as i64 + offset,
+        };
+        if position < 0 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Seek before the start of the file",
+            ));
+        }
+        self.position = position as usize;
+        Ok(position as u64)
+    }
+}
+
+#[derive(Debug)]
+enum Descriptor {
+    Stdin,
+    Stdout,
+    Stderr,
+    File {
+        handle: Box<dyn FileHandle>,
+        mode: OpenMode,
+    },
+}
+
+/// The open files of a running program, indexed by descriptor.
+///
+/// Descriptors 0, 1 and 2 start out as the host's standard input, output and
+/// error.
+#[derive(Debug)]
+pub struct FileTable {
+    filesystem: Box<dyn FileSystem>,
+    descriptors: Vec<Option<Descriptor>>,
+}
+impl FileTable {
+    pub fn new(filesystem: Box<dyn FileSystem>) -> Self {
+        Self {
+            filesystem,
+            descriptors: vec![
+                Some(Descriptor::Stdin),
+                Some(Descriptor::Stdout),
+                Some(Descriptor::Stderr),
+            ],
+        }
+    }
+
+    fn insert(&mut self, descriptor: Descriptor) -> usize {
+        match self.descriptors.iter().position(Option::is_none) {
+            Some(fd) => {
+                self.descriptors[fd] = Some(descriptor);
+                fd
+            }
+            None => {
+                self.descriptors.push(Some(descriptor));
+                self.descriptors.len() - 1
+            }
+        }
+    }
+
+    fn get(&mut self, fd: i64) -> io::Result<&mut Descriptor> {
+        let descriptors = &mut self.descriptors;
+        usize::try_from(fd)
+            .ok()
+            .and_then(move |fd| descriptors.get_mut(fd))
+            .and_then(Option::as_mut)
+            .ok_or_else(|| {
+                io::Error::new(ErrorKind::NotFound, format!("Bad file descriptor {}", fd))
+            })
+    }
+
+    pub fn open(&mut self, path: &str, mode: OpenMode) -> io::Result<usize> {
+        let handle = self.filesystem.open(path, mode)?;
+        Ok(self.insert(Descriptor::File { handle, mode }))
+    }
+
+    pub fn create(&mut self, path: &str) -> io::Result<usize> {
+        let handle = self.filesystem.create(path)?;
+        Ok(self.insert(Descriptor::File {
+            handle,
+            mode: OpenMode::Write,
+        }))
+    }
+
+    pub fn read(&mut self, fd: i64, buf: &mut [u8]) -> io::Result<usize> {
+        match self.get(fd)? {
+            Descriptor::Stdin => stdin().read(buf),
+            Descriptor::File { handle, mode } if mode.readable() => handle.read(buf),
+            _ => Err(io::Error::new(
+                ErrorKind::PermissionDenied,
+                format!("File descriptor {} is not open for reading", fd),
+            )),
+        }
+    }
+
+    pub fn write(&mut self, fd: i64, buf: &[u8]) -> io::Result<usize> {
+        match self.get(fd)? {
+            Descriptor::Stdout => {
+                let mut stdout = stdout();
+                stdout.write_all(buf)?;
+                stdout.flush()?;
+                Ok(buf.len())
+            }
+            Descriptor::Stderr => stderr().write_all(buf).map(|_| buf.len()),
+            Descriptor::File { handle, mode } if mode.writable() => {
+                handle.write_all(buf).map(|_| buf.len())
+            }
+            _ => Err(io::Error::new(
+                ErrorKind::PermissionDenied,
+                format!("File descriptor {} is not open for writing", fd),
+            )),
+        }
+    }
+
+    pub fn seek(&mut self, fd: i64, pos: SeekFrom) -> io::Result<u64> {
+        match self.get(fd)? {
+            Descriptor::File { handle, .. } => handle.seek(pos),
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("File descriptor {} can not seek", fd),
+            )),
+        }
+    }
+
+    pub fn close(&mut self, fd: i64) -> io::Result<()> {
+        self.get(fd)?;
+        self.descriptors[fd as usize] = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory to be the root of a DirectoryFileSystem
+    fn directory(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("brust-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("root")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn directory_files_can_be_created_and_read_back() {
+        let dir = directory("roundtrip");
+        let mut files = FileTable::new(Box::new(
+            DirectoryFileSystem::new(dir.join("root")).unwrap(),
+        ));
+
+        let fd = files.create("out.txt").unwrap() as i64;
+        files.write(fd, b"hello").unwrap();
+        files.close(fd).unwrap();
+
+        let fd = files.open("out.txt", OpenMode::Read).unwrap() as i64;
+        let mut buf = [0; 5];
+        assert_eq!(files.read(fd, &mut buf).unwrap(), 5);
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[test]
+    fn paths_out_of_the_root_are_refused() {
+        let dir = directory("escape");
+        fs::write(dir.join("secret.txt"), "secret").unwrap();
+        let mut filesystem = DirectoryFileSystem::new(dir.join("root")).unwrap();
+
+        for path in ["../secret.txt", "/etc/passwd", "a/../../secret.txt"] {
+            let e = filesystem.open(path, OpenMode::Read).unwrap_err();
+            assert_eq!(e.kind(), ErrorKind::PermissionDenied, "{}", path);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_out_of_the_root_are_refused() {
+        let dir = directory("symlinks");
+        fs::write(dir.join("secret.txt"), "secret").unwrap();
+        std::os::unix::fs::symlink(dir.join("secret.txt"), dir.join("root/existing")).unwrap();
+        std::os::unix::fs::symlink(dir.join("created.txt"), dir.join("root/dangling")).unwrap();
+        let mut filesystem = DirectoryFileSystem::new(dir.join("root")).unwrap();
+
+        let e = filesystem.open("existing", OpenMode::Read).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::PermissionDenied);
+        let e = filesystem.create("existing").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(
+            fs::read_to_string(dir.join("secret.txt")).unwrap(),
+            "secret"
+        );
+
+        let e = filesystem.create("dangling").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::PermissionDenied);
+        assert!(!dir.join("created.txt").exists());
+    }
+
+    #[test]
+    fn memory_files_keep_what_was_written() {
+        let mut files = FileTable::new(Box::new(MemoryFileSystem::new().with_file("in", b"abc")));
+
+        let fd = files.open("in", OpenMode::ReadWrite).unwrap() as i64;
+        files.seek(fd, SeekFrom::End(0)).unwrap();
+        files.write(fd, b"def").unwrap();
+        files.seek(fd, SeekFrom::Start(0)).unwrap();
+        let mut buf = [0; 6];
+        assert_eq!(files.read(fd, &mut buf).unwrap(), 6);
+        assert_eq!(&buf, b"abcdef");
+
+        let fd = files.open("in", OpenMode::Read).unwrap() as i64;
+        assert!(files.write(fd, b"x").is_err());
+        assert!(files.open("missing", OpenMode::Read).is_err());
+    }
+}
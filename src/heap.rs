@@ -0,0 +1,256 @@
+use std::fmt::{Display, Formatter};
+
+use crate::ast::Const;
+
+/// A handle to a vector allocated with `getvec`.
+///
+/// The generation is bumped every time its slot is released, so a handle
+/// that outlives its allocation can be told apart from one to whatever
+/// reuses the slot afterwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd)]
+pub struct HeapRef {
+    index: usize,
+    generation: u32,
+}
+
+impl Display for HeapRef {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "vector {}", self.index)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeapError {
+    DoubleFree,
+    UseAfterFree,
+    SizeMismatch { allocated: usize, released: usize },
+    OutOfBounds { index: i64, len: usize },
+    OutOfMemory { size: usize, left: usize },
+}
+impl Display for HeapError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Self::DoubleFree => write!(f, "Vector released twice"),
+            Self::UseAfterFree => write!(f, "Vector used after it was released"),
+            Self::SizeMismatch {
+                allocated,
+                released,
+            } => write!(
+                f,
+                "Vector allocated with getvec({}) released with size {}",
+                allocated, released
+            ),
+            Self::OutOfBounds { index, len } => write!(
+                f,
+                "Index {} out of bounds for vector of {} words",
+                index, len
+            ),
+            Self::OutOfMemory { size, left } => write!(
+                f,
+                "Not enough memory for getvec({}), only {} words are left",
+                size, left
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Slot {
+    generation: u32,
+    words: Option<Vec<Const>>,
+}
+
+/// The most words the vectors allocated at once can have between them, so a
+/// program asking for too much gets an error rather than taking the
+/// interpreter down with it
+const MAX_WORDS: usize = 1 << 22;
+
+#[derive(Debug, Default)]
+pub struct Heap {
+    slots: Vec<Slot>,
+    // Words in the vectors that have not been released
+    used: usize,
+}
+impl Heap {
+    pub fn new() -> Self {
+        Self {
+            slots: vec![],
+            used: 0,
+        }
+    }
+
+    /// Allocates `size + 1` zeroed words, matching B's `getvec(size)` which
+    /// may be indexed from 0 to `size` inclusive.
+    pub fn alloc(&mut self, size: usize) -> Result<HeapRef, HeapError> {
+        let left = MAX_WORDS - self.used;
+        if size >= left {
+            return Err(HeapError::OutOfMemory { size, left });
+        }
+        self.used += size + 1;
+        let words = Some(vec![Const::Integer(0); size + 1]);
+
+        if let Some(index) = self.slots.iter().position(|slot| slot.words.is_none()) {
+            let slot = &mut self.slots[index];
+            slot.words = words;
+            return Ok(HeapRef {
+                index,
+                generation: slot.generation,
+            });
+        }
+
+        self.slots.push(Slot {
+            generation: 0,
+            words,
+        });
+        Ok(HeapRef {
+            index: self.slots.len() - 1,
+            generation: 0,
+        })
+    }
+
+    pub fn free(&mut self, vector: HeapRef, size: Option<usize>) -> Result<(), HeapError> {
+        let slot = &mut self.slots[vector.index];
+        if slot.generation != vector.generation {
+            return Err(HeapError::DoubleFree);
+        }
+        let allocated = slot.words.as_ref().map(Vec::len).unwrap_or(0) - 1;
+        match size {
+            Some(released) if released != allocated => Err(HeapError::SizeMismatch {
+                allocated,
+                released,
+            }),
+            _ => {
+                slot.words = None;
+                slot.generation += 1;
+                self.used -= allocated + 1;
+                Ok(())
+            }
+        }
+    }
+
+    pub fn words(&self, vector: HeapRef) -> Result<&Vec<Const>, HeapError> {
+        let slot = &self.slots[vector.index];
+        match &slot.words {
+            Some(words) if slot.generation == vector.generation => Ok(words),
+            _ => Err(HeapError::UseAfterFree),
+        }
+    }
+
+    fn words_mut(&mut self, vector: HeapRef) -> Result<&mut Vec<Const>, HeapError> {
+        let slot = &mut self.slots[vector.index];
+        match &mut slot.words {
+            Some(words) if slot.generation == vector.generation => Ok(words),
+            _ => Err(HeapError::UseAfterFree),
+        }
+    }
+
+    pub fn get(&self, vector: HeapRef, index: i64) -> Result<Const, HeapError> {
+        let words = self.words(vector)?;
+        if index < 0 || index as usize >= words.len() {
+            return Err(HeapError::OutOfBounds {
+                index,
+                len: words.len(),
+            });
+        }
+        Ok(words[index as usize].clone())
+    }
+
+    pub fn set(&mut self, vector: HeapRef, index: i64, value: Const) -> Result<(), HeapError> {
+        let words = self.words_mut(vector)?;
+        if index < 0 || index as usize >= words.len() {
+            return Err(HeapError::OutOfBounds {
+                index,
+                len: words.len(),
+            });
+        }
+        words[index as usize] = value;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vectors_have_a_word_past_their_size() {
+        let mut heap = Heap::new();
+        let vector = heap.alloc(2).unwrap();
+        assert_eq!(heap.words(vector).unwrap().len(), 3);
+        heap.set(vector, 2, Const::Integer(5)).unwrap();
+        assert_eq!(heap.get(vector, 2).unwrap(), Const::Integer(5));
+        assert_eq!(heap.get(vector, 0).unwrap(), Const::Integer(0));
+        assert_eq!(
+            heap.get(vector, 3),
+            Err(HeapError::OutOfBounds { index: 3, len: 3 })
+        );
+        assert_eq!(
+            heap.set(vector, -1, Const::Integer(1)),
+            Err(HeapError::OutOfBounds { index: -1, len: 3 })
+        );
+    }
+
+    #[test]
+    fn released_vectors_cannot_be_used() {
+        let mut heap = Heap::new();
+        let vector = heap.alloc(4).unwrap();
+        heap.free(vector, Some(4)).unwrap();
+        assert_eq!(heap.get(vector, 0), Err(HeapError::UseAfterFree));
+        assert_eq!(
+            heap.set(vector, 0, Const::Integer(1)),
+            Err(HeapError::UseAfterFree)
+        );
+        assert_eq!(heap.words(vector), Err(HeapError::UseAfterFree));
+    }
+
+    #[test]
+    fn releasing_twice_is_an_error() {
+        let mut heap = Heap::new();
+        let vector = heap.alloc(1).unwrap();
+        heap.free(vector, None).unwrap();
+        assert_eq!(heap.free(vector, None), Err(HeapError::DoubleFree));
+    }
+
+    #[test]
+    fn old_handles_do_not_reach_a_reused_slot() {
+        let mut heap = Heap::new();
+        let old = heap.alloc(1).unwrap();
+        heap.free(old, None).unwrap();
+        let new = heap.alloc(1).unwrap();
+        heap.set(new, 0, Const::Integer(9)).unwrap();
+        assert_eq!(heap.get(old, 0), Err(HeapError::UseAfterFree));
+        assert_eq!(heap.free(old, None), Err(HeapError::DoubleFree));
+        assert_eq!(heap.get(new, 0).unwrap(), Const::Integer(9));
+    }
+
+    #[test]
+    fn releasing_with_the_wrong_size_is_an_error() {
+        let mut heap = Heap::new();
+        let vector = heap.alloc(3).unwrap();
+        assert_eq!(
+            heap.free(vector, Some(2)),
+            Err(HeapError::SizeMismatch {
+                allocated: 3,
+                released: 2
+            })
+        );
+        assert_eq!(heap.get(vector, 3).unwrap(), Const::Integer(0));
+    }
+    #[test]
+    fn vectors_cannot_take_more_than_the_memory_left() {
+        let mut heap = Heap::new();
+        assert_eq!(
+            heap.alloc(usize::MAX),
+            Err(HeapError::OutOfMemory {
+                size: usize::MAX,
+                left: MAX_WORDS
+            })
+        );
+        let half = heap.alloc(MAX_WORDS / 2 - 1).unwrap();
+        assert!(heap.alloc(MAX_WORDS / 2).is_err());
+        // Released words can be allocated again
+        heap.free(half, None).unwrap();
+        heap.alloc(MAX_WORDS - 1).unwrap();
+        assert!(heap.alloc(0).is_err());
+    }
+}
@@ -1,13 +1,65 @@
 use std::{
     collections::HashMap,
+    convert::TryFrom,
     fs,
-    io::{Read, Stdin, Stdout, StdoutLock, Write},
+    io::{stdout, Read, SeekFrom, Stdin, Stdout, StdoutLock, Write},
     path::Path,
+    str::FromStr,
 };
 
-use crate::{ast::*, Parser};
+use lalrpop_util::ParseError;
 
-type BuiltinFunction = fn(Vec<Expression>) -> Expression;
+use crate::{
+    ast::*,
+    environment::{Environment, SystemEnvironment},
+    filesystem::{FileSystem, FileTable, MemoryFileSystem, OpenMode},
+    heap::{Heap, HeapRef},
+    source::{FileId, SourceMap, Span},
+    Parser,
+};
+
+mod check;
+mod diagnostic;
+mod error;
+mod eval;
+mod format;
+mod lint;
+mod maps;
+mod reflection;
+mod strings;
+
+pub use check::{check, Checker};
+pub use diagnostic::{Diagnostic, ErrorFormat, Severity};
+pub use error::{Details, ErrorKind, Fix, Label, Location, RuntimeError, SyntaxError, TraceFrame};
+pub use lint::{Level, Lint, Linter, Warning};
+
+type BuiltinFunction = fn(&mut Interpreter, Vec<Expression>) -> Result<Expression, RuntimeError>;
+
+// Where reset() continues, the statement after the one that called setexit()
+// in the block it was called from
+#[derive(Debug, Clone, Copy)]
+struct ExitPoint {
+    block: usize,
+    statement: usize,
+}
+
+/// A function call in progress
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub function: Identifier,
+    // Where the call is, None when the host made it
+    pub call_site: Option<Span>,
+    pub arguments: Vec<Expression>,
+    // What the arguments came to, filled in once they are worked out
+    pub values: Vec<Const>,
+}
+
+// Somewhere a value can be stored, either a variable or a word of a vector
+// on the heap
+enum Place {
+    Variable(Identifier),
+    HeapWord(HeapRef, i64),
+}
 
 #[derive(Debug, Clone)]
 pub struct Function {
@@ -15,6 +67,7 @@ pub struct Function {
     body: Statement,
     builtin: Option<BuiltinFunction>,
 }
+#[allow(non_snake_case)]
 impl Function {
     fn Builtin() -> Self {
         Self {
@@ -27,10 +80,11 @@ impl Function {
     pub fn Puts() -> Self {
         let mut func = Self::Builtin();
 
-        func.builtin = Some(|args| {
-            args.iter()
-                .for_each(|expr| println!("{}", expr.expect_const().unwrap()));
-            return Expression::Constant(Const::Integer(0));
+        func.builtin = Some(|interpreter, args| {
+            for arg in args {
+                println!("{}", interpreter.show(&value_arg("puts", arg)?));
+            }
+            Ok(Expression::constant(Const::Integer(0)))
         });
 
         func
@@ -39,28 +93,537 @@ impl Function {
     pub fn Format() -> Self {
         let mut func = Self::Builtin();
 
-        func.builtin = Some(|args| {
-            if let Const::String(s) = args.get(0).unwrap().expect_const().unwrap() {
-                let mut s = s;
-                let mut args = match args.get(1).map(|a| a.expect_const()).flatten() {
-                    Some(Const::Vector(mut v)) => v
-                        .into_iter()
-                        .map(|boxed| Box::leak(boxed).clone().expect_const().unwrap())
-                        .collect::<Vec<Const>>(),
-                    _ => panic!("second argument of format must be a vector constant"),
-                };
+        func.builtin = Some(|interpreter, args| {
+            let s = match args.first().and_then(Expression::expect_const) {
+                Some(Const::String(s)) => s,
+                _ => return Err(argument_error("First argument of format must be a string")),
+            };
+            let args = match args.get(1).and_then(Expression::expect_const) {
+                Some(Const::Vector(v)) => v
+                    .into_iter()
+                    .map(|boxed| value_arg("format", *boxed))
+                    .collect::<Result<Vec<Const>, RuntimeError>>()?,
+                Some(Const::HeapVector(vector)) => interpreter.heap.words(vector)?.clone(),
+                None => vec![],
+                _ => return Err(argument_error("Second argument of format must be a vector")),
+            };
+
+            let s = format::format(terminated(&s), &args, interpreter)
+                .map_err(|e| argument_error(format!("format: {}", e)))?;
+            Ok(Expression::constant(Const::String(s)))
+        });
+
+        func
+    }
+
+    /// `dump(x)` prints what kind of value `x` is along with all of it and
+    /// returns `x`
+    pub fn Dump() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let value = args
+                .first()
+                .and_then(Expression::expect_const)
+                .ok_or_else(|| argument_error("dump needs a value"))?;
+            println!(
+                "{}: {}",
+                value.kind(),
+                value.render(Some(&interpreter.heap))
+            );
+            Ok(Expression::constant(value))
+        });
+
+        func
+    }
 
-                while (s.contains("{}")) {
-                    s = s.replacen("{}", args.remove(0).to_string().as_str(), 1);
+    pub fn Printn() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let (n, base) = radix_args("printn", &args)?;
+            print!("{}", radix_text(&n, base, interpreter.word_size));
+            stdout().flush().unwrap();
+            Ok(Expression::constant(Const::Integer(0)))
+        });
+
+        func
+    }
+
+    pub fn Numstr() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let (n, base) = radix_args("numstr", &args)?;
+            Ok(Expression::constant(Const::String(radix_text(
+                &n,
+                base,
+                interpreter.word_size,
+            ))))
+        });
+
+        func
+    }
+
+    pub fn Getvec() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let size = match args.first().and_then(Expression::expect_const) {
+                Some(Const::Integer(size)) if size >= 0 => size as usize,
+                _ => {
+                    return Err(argument_error(
+                        "Argument of getvec must be a non negative integer",
+                    ))
                 }
+            };
+            Ok(Expression::constant(Const::HeapVector(
+                interpreter.heap.alloc(size)?,
+            )))
+        });
+
+        func
+    }
+
+    pub fn Rlsevec() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let vector = match args.first().and_then(Expression::expect_const) {
+                Some(Const::HeapVector(vector)) => vector,
+                _ => {
+                    return Err(argument_error(
+                        "First argument of rlsevec must be a vector from getvec",
+                    ))
+                }
+            };
+            let size = match args.get(1).and_then(Expression::expect_const) {
+                Some(Const::Integer(size)) => Some(size as usize),
+                None => None,
+                _ => {
+                    return Err(argument_error(
+                        "Second argument of rlsevec must be an integer",
+                    ))
+                }
+            };
+            interpreter.heap.free(vector, size)?;
+            Ok(Expression::constant(Const::Integer(0)))
+        });
+
+        func
+    }
+
+    pub fn Exit() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|_, args| {
+            let status = match args.first().and_then(Expression::expect_const) {
+                Some(Const::Integer(status)) => status,
+                None => 0,
+                _ => return Err(argument_error("Argument of exit must be an integer")),
+            };
+            Err(RuntimeError::new(
+                ErrorKind::Exit(status),
+                format!("exit({}) was called", status),
+            ))
+        });
+
+        func
+    }
+
+    pub fn Open() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let path = path_arg("open", &args)?;
+            let mode = match args.get(1).and_then(Expression::expect_const) {
+                Some(Const::Integer(mode)) => OpenMode::from_word(mode),
+                None => Some(OpenMode::Read),
+                _ => None,
+            }
+            .ok_or_else(|| argument_error("Second argument of open must be 0, 1 or 2"))?;
+
+            Ok(io_result(
+                interpreter.files.open(&path, mode).map(|fd| fd as i64),
+            ))
+        });
+
+        func
+    }
+
+    pub fn Creat() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let path = path_arg("creat", &args)?;
+            Ok(io_result(
+                interpreter.files.create(&path).map(|fd| fd as i64),
+            ))
+        });
+
+        func
+    }
+
+    pub fn Read() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let fd = integer_arg("read", &args, 0)?;
+            let vector = match args.get(1).and_then(Expression::expect_const) {
+                Some(Const::HeapVector(vector)) => vector,
+                _ => {
+                    return Err(argument_error(
+                        "Second argument of read must be a vector from getvec",
+                    ))
+                }
+            };
+            let len = interpreter.heap.words(vector)?.len();
+            let count = match args.get(2).and_then(Expression::expect_const) {
+                Some(Const::Integer(count)) if count >= 0 && count as usize <= len => {
+                    count as usize
+                }
+                None => len,
+                _ => {
+                    return Err(argument_error(
+                        "Third argument of read must be a count that fits in the vector",
+                    ))
+                }
+            };
+
+            let mut buf = vec![0; count];
+            let read = interpreter.files.read(fd, &mut buf);
+            if let Ok(read) = read {
+                for (i, byte) in buf[..read].iter().enumerate() {
+                    interpreter
+                        .heap
+                        .set(vector, i as i64, Const::Integer(*byte as i64))?;
+                }
+            }
+            Ok(io_result(read.map(|read| read as i64)))
+        });
+
+        func
+    }
+
+    pub fn Write() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let fd = integer_arg("write", &args, 0)?;
+            let bytes: Vec<u8> = match args.get(1).and_then(Expression::expect_const) {
+                Some(Const::HeapVector(vector)) => interpreter
+                    .heap
+                    .words(vector)?
+                    .iter()
+                    .map(|word| match word {
+                        Const::Integer(i) => Ok(*i as u8),
+                        _ => Err(argument_error("write: vector contains a non integer value")),
+                    })
+                    .collect::<Result<_, _>>()?,
+                Some(Const::String(s)) => terminated(&s).bytes().collect(),
+                _ => {
+                    return Err(argument_error(
+                        "Second argument of write must be a string or a vector",
+                    ))
+                }
+            };
+            let count = match args.get(2).and_then(Expression::expect_const) {
+                Some(Const::Integer(count)) if count >= 0 && count as usize <= bytes.len() => {
+                    count as usize
+                }
+                None => bytes.len(),
+                _ => {
+                    return Err(argument_error(
+                        "Third argument of write must be a count that fits in the buffer",
+                    ))
+                }
+            };
+
+            Ok(io_result(
+                interpreter
+                    .files
+                    .write(fd, &bytes[..count])
+                    .map(|written| written as i64),
+            ))
+        });
+
+        func
+    }
+
+    pub fn Seek() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let fd = integer_arg("seek", &args, 0)?;
+            let offset = integer_arg("seek", &args, 1)?;
+            let pos = match integer_arg("seek", &args, 2)? {
+                0 => SeekFrom::Start(offset.max(0) as u64),
+                1 => SeekFrom::Current(offset),
+                2 => SeekFrom::End(offset),
+                _ => return Err(argument_error("Third argument of seek must be 0, 1 or 2")),
+            };
+
+            Ok(io_result(
+                interpreter.files.seek(fd, pos).map(|offset| offset as i64),
+            ))
+        });
+
+        func
+    }
+
+    pub fn Close() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let fd = integer_arg("close", &args, 0)?;
+            Ok(io_result(interpreter.files.close(fd).map(|_| 0)))
+        });
+
+        func
+    }
+
+    /// `assert(condition)` or `assert(condition, message)`, a failure is an
+    /// error showing the condition as it was written
+    pub fn Assert() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let holds = args
+                .first()
+                .and_then(Expression::expect_const)
+                .ok_or_else(|| argument_error("assert needs a condition"))?
+                .truthy();
+            if holds {
+                return Ok(Expression::constant(Const::Integer(1)));
+            }
+
+            // The condition as it was written, or rebuilt from the tree for
+            // one that did not come from the source
+            let frame = interpreter.frames.last();
+            let condition = frame.and_then(|frame| frame.arguments.first());
+            let written = condition
+                .and_then(Expression::span)
+                .and_then(|span| interpreter.sources.text(span));
+            let mut report = match (written, condition) {
+                (Some(written), _) => format!("Assertion failed: {}", written),
+                (None, Some(condition)) => format!("Assertion failed: {}", condition),
+                (None, None) => "Assertion failed".to_string(),
+            };
+            if let Some(message) = args.get(1).and_then(Expression::expect_const) {
+                report.push_str(&format!(", {}", message));
+            }
+            Err(RuntimeError::new(ErrorKind::AssertionFailed, report))
+        });
+
+        func
+    }
+
+    /// `setexit()`, saves the current point for `reset()` to return to
+    pub fn Setexit() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, _| {
+            let (block, statement) = *interpreter.blocks.last().ok_or_else(|| {
+                RuntimeError::new(
+                    ErrorKind::Unsupported,
+                    "setexit called outside of a function",
+                )
+            })?;
+            interpreter.exit_point = Some(ExitPoint { block, statement });
+            Ok(Expression::constant(Const::Integer(0)))
+        });
+
+        func
+    }
+
+    /// `reset()`, abandons every call made since the last `setexit()` and
+    /// continues after the statement that called it
+    pub fn Reset() -> Self {
+        let mut func = Self::Builtin();
 
-                return Expression::Constant(Const::String(s));
+        func.builtin = Some(|interpreter, _| {
+            let point = interpreter.exit_point.ok_or_else(|| {
+                RuntimeError::new(ErrorKind::InvalidReset, "reset called without a setexit")
+            })?;
+            if !interpreter.blocks.iter().any(|(b, _)| *b == point.block) {
+                return Err(RuntimeError::new(
+                    ErrorKind::InvalidReset,
+                    "reset called after the function that called setexit returned",
+                ));
             }
-            panic!("First argument of format must be a string constant");
+            Err(RuntimeError::new(ErrorKind::Reset, "reset() was called"))
+        });
+
+        func
+    }
+
+    /// `float(i)`, the integer `i` as a floating point word
+    pub fn Float() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|_, args| {
+            Ok(Expression::constant(Const::from_float(
+                integer_arg("float", &args, 0)? as f64,
+            )))
         });
 
         func
     }
+
+    /// `fix(f)`, the floating point word `f` truncated to an integer
+    pub fn Fix() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|_, args| {
+            let f = Const::Integer(integer_arg("fix", &args, 0)?).float();
+            Ok(Expression::constant(Const::Integer(f as i64)))
+        });
+
+        func
+    }
+
+    pub fn Time() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, _| {
+            Ok(Expression::constant(Const::Integer(
+                interpreter.environment.time(),
+            )))
+        });
+
+        func
+    }
+
+    pub fn Clock() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, _| {
+            Ok(Expression::constant(Const::Integer(
+                interpreter.environment.clock(),
+            )))
+        });
+
+        func
+    }
+
+    pub fn Rand() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, _| {
+            Ok(Expression::constant(Const::Integer(
+                interpreter.environment.rand(),
+            )))
+        });
+
+        func
+    }
+
+    pub fn Srand() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let seed = integer_arg("srand", &args, 0)?;
+            interpreter.environment.srand(seed);
+            Ok(Expression::constant(Const::Integer(0)))
+        });
+
+        func
+    }
+}
+
+fn argument_error<S: ToString>(message: S) -> RuntimeError {
+    RuntimeError::new(ErrorKind::InvalidArgument, message)
+}
+
+fn value_arg(name: &str, arg: Expression) -> Result<Const, RuntimeError> {
+    arg.expect_const()
+        .ok_or_else(|| argument_error(format!("{} was given {} which is not a value", name, arg)))
+}
+
+fn integer_arg(name: &str, args: &[Expression], position: usize) -> Result<i64, RuntimeError> {
+    match args.get(position).and_then(Expression::expect_const) {
+        Some(Const::Integer(i)) => Ok(i),
+        _ => Err(argument_error(format!(
+            "Argument {} of {} must be an integer",
+            position + 1,
+            name
+        ))),
+    }
+}
+
+fn path_arg(name: &str, args: &[Expression]) -> Result<String, RuntimeError> {
+    match args.first().and_then(Expression::expect_const) {
+        Some(Const::String(path)) => Ok(terminated(&path).into()),
+        _ => Err(argument_error(format!(
+            "First argument of {} must be a string",
+            name
+        ))),
+    }
+}
+
+// File builtins follow UNIX and return -1 when the operation fails
+fn io_result(result: std::io::Result<i64>) -> Expression {
+    Expression::constant(Const::Integer(result.unwrap_or(-1)))
+}
+
+fn radix_args(name: &str, args: &[Expression]) -> Result<(Const, u32), RuntimeError> {
+    let n = match args.first().and_then(Expression::expect_const) {
+        Some(n @ Const::Integer(_)) | Some(n @ Const::BigInteger(_)) => n,
+        _ => {
+            return Err(argument_error(format!(
+                "First argument of {} must be an integer",
+                name
+            )))
+        }
+    };
+    let base = match args.get(1).and_then(Expression::expect_const) {
+        Some(Const::Integer(base)) if (2..=36).contains(&base) => base as u32,
+        None => 10,
+        _ => {
+            return Err(argument_error(format!(
+                "Second argument of {} must be a base between 2 and 36",
+                name
+            )))
+        }
+    };
+    Ok((n, base))
+}
+
+/// Renders an integer in `base`, big integers are not words so they are
+/// always written signed
+fn radix_text(n: &Const, base: u32, word_size: u32) -> String {
+    match n {
+        Const::Integer(i) => radix_string(*i, base, word_size),
+        Const::BigInteger(i) => i.to_str_radix(base),
+        _ => unreachable!("radix_text called with a non integer"),
+    }
+}
+
+/// Renders `n` in `base`, treating it as a `word_size` bit word.
+///
+/// Decimal output is signed, any other base prints the word's bit pattern
+/// unsigned, so `printn(-1, 8)` gives `177777` on a 16 bit word.
+fn radix_string(n: i64, base: u32, word_size: u32) -> String {
+    let shift = 64 - word_size;
+    let (negative, mut magnitude) = if base == 10 {
+        let n = (n << shift) >> shift;
+        (n < 0, n.unsigned_abs())
+    } else {
+        (false, ((n as u64) << shift) >> shift)
+    };
+
+    let mut digits = vec![];
+    loop {
+        digits.push(std::char::from_digit((magnitude % base as u64) as u32, base).unwrap());
+        magnitude /= base as u64;
+        if magnitude == 0 {
+            break;
+        }
+    }
+    if negative {
+        digits.push('-');
+    }
+    digits.into_iter().rev().collect()
 }
 
 #[derive(Debug)]
@@ -68,6 +631,8 @@ pub struct Scope {
     functions: HashMap<Identifier, Function>,
     variables: HashMap<Identifier, Option<Const>>,
     extern_variables: HashMap<Identifier, Option<Const>>,
+    // Where each auto was declared, for reads before it is assigned
+    declarations: HashMap<Identifier, Span>,
 }
 impl Scope {
     pub fn new() -> Self {
@@ -75,6 +640,7 @@ impl Scope {
             functions: HashMap::new(),
             variables: HashMap::new(),
             extern_variables: HashMap::new(),
+            declarations: HashMap::new(),
         }
     }
 
@@ -82,12 +648,54 @@ impl Scope {
         let mut functions = HashMap::new();
         functions.insert(Identifier::Name("puts".into()), Function::Puts());
         functions.insert(Identifier::Name("format".into()), Function::Format());
+        functions.insert(Identifier::Name("dump".into()), Function::Dump());
+        functions.insert(Identifier::Name("printn".into()), Function::Printn());
+        functions.insert(Identifier::Name("numstr".into()), Function::Numstr());
+        functions.insert(Identifier::Name("getvec".into()), Function::Getvec());
+        functions.insert(Identifier::Name("rlsevec".into()), Function::Rlsevec());
+        functions.insert(Identifier::Name("exit".into()), Function::Exit());
+        functions.insert(Identifier::Name("open".into()), Function::Open());
+        functions.insert(Identifier::Name("creat".into()), Function::Creat());
+        functions.insert(Identifier::Name("read".into()), Function::Read());
+        functions.insert(Identifier::Name("write".into()), Function::Write());
+        functions.insert(Identifier::Name("seek".into()), Function::Seek());
+        functions.insert(Identifier::Name("close".into()), Function::Close());
+        functions.insert(Identifier::Name("assert".into()), Function::Assert());
+        functions.insert(Identifier::Name("setexit".into()), Function::Setexit());
+        functions.insert(Identifier::Name("reset".into()), Function::Reset());
+        functions.insert(Identifier::Name("float".into()), Function::Float());
+        functions.insert(Identifier::Name("fix".into()), Function::Fix());
+        functions.insert(Identifier::Name("time".into()), Function::Time());
+        functions.insert(Identifier::Name("clock".into()), Function::Clock());
+        functions.insert(Identifier::Name("rand".into()), Function::Rand());
+        functions.insert(Identifier::Name("srand".into()), Function::Srand());
+        functions.insert(Identifier::Name("char".into()), Function::Char());
+        functions.insert(Identifier::Name("strlen".into()), Function::Strlen());
+        functions.insert(Identifier::Name("strcmp".into()), Function::Strcmp());
+        functions.insert(Identifier::Name("strcpy".into()), Function::Strcpy());
+        functions.insert(Identifier::Name("strcat".into()), Function::Strcat());
+        functions.insert(Identifier::Name("substr".into()), Function::Substr());
+        functions.insert(Identifier::Name("strchr".into()), Function::Strchr());
+        functions.insert(Identifier::Name("strstr".into()), Function::Strstr());
+        functions.insert(Identifier::Name("mapnew".into()), Function::Mapnew());
+        functions.insert(Identifier::Name("mapget".into()), Function::Mapget());
+        functions.insert(Identifier::Name("mapset".into()), Function::Mapset());
+        functions.insert(Identifier::Name("maphas".into()), Function::Maphas());
+        functions.insert(Identifier::Name("mapdel".into()), Function::Mapdel());
+        functions.insert(Identifier::Name("mapkeys".into()), Function::Mapkeys());
+        functions.insert(Identifier::Name("mapsize".into()), Function::Mapsize());
+        functions.insert(Identifier::Name("call".into()), Function::Call());
+        functions.insert(Identifier::Name("hasfunc".into()), Function::Hasfunc());
+        functions.insert(Identifier::Name("funcs".into()), Function::Funcs());
+        functions.insert(Identifier::Name("eval".into()), Function::Eval());
+        functions.insert(Identifier::Name("iserr".into()), Function::Iserr());
 
         let mut variables = HashMap::new();
         Self {
             functions,
             variables,
             extern_variables: HashMap::new(),
+            declarations: HashMap::new(),
         }
     }
     fn has_var(&self, ident: &Identifier) -> bool {
@@ -100,6 +708,14 @@ impl Scope {
     fn get_var(&self, ident: &Identifier) -> Option<Const> {
         self.variables.get(ident).cloned().flatten()
     }
+
+    // Where an auto that has not been given a value yet was declared
+    fn unassigned(&self, ident: &Identifier) -> Option<Span> {
+        match self.variables.get(ident) {
+            Some(None) => self.declarations.get(ident).copied(),
+            _ => None,
+        }
+    }
     fn get_func(&self, ident: &Identifier) -> Option<&Function> {
         self.functions.get(ident)
     }
@@ -108,11 +724,61 @@ impl Scope {
     }
 }
 
+/// What an `auto` holds before it is assigned. Real B leaves whatever was in
+/// memory, which hides bugs, so none of these copy that
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Uninitialized {
+    /// Reading it is an error that says where it was declared
+    Error,
+    /// It starts at 0, which is what most B programs that read one expect
+    Zero,
+    /// It holds a marker that can be copied into other variables, but any
+    /// other use of it is the error reading it is under `Error`, so it turns
+    /// up where the value is used rather than where it is read
+    Poison,
+}
+impl FromStr for Uninitialized {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "error" => Ok(Uninitialized::Error),
+            "zero" => Ok(Uninitialized::Zero),
+            "poison" => Ok(Uninitialized::Poison),
+            _ => Err(format!("There is no uninitialized mode called {}", name)),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Interpreter {
-    buffer: String,
+    // Sources of the program being run and of anything passed to eval
+    sources: SourceMap,
+    // The one the program was started from
+    file: FileId,
+    // Width in bits of a B word, 64 unless configured otherwise
+    word_size: u32,
+    // Whether integers grow past a word instead of wrapping
+    big_integers: bool,
+    // Lints set to something other than warn
+    lints: HashMap<Lint, Level>,
+    error_format: ErrorFormat,
+    uninitialized: Uninitialized,
+    heap: Heap,
+    files: FileTable,
+    environment: Box<dyn Environment>,
+    // Passed to main as argc and argv
+    args: Vec<String>,
     // 0 is global
     scopes: Vec<Scope>,
+    frames: Vec<Frame>,
+    // Where the call about to be made is in the source
+    call_site: Option<Span>,
+    // Compound statements being run, innermost last, with the index of the
+    // statement each is on
+    blocks: Vec<(usize, usize)>,
+    next_block: usize,
+    exit_point: Option<ExitPoint>,
     // stdout: Stdout,
     // // stdin: Stdin,
 }
@@ -154,65 +820,247 @@ impl Interpreter {
     // }
     pub fn new() -> Self {
         Self {
-            buffer: String::new(),
+            sources: SourceMap::new(),
+            file: FileId::default(),
+            word_size: 64,
+            big_integers: false,
+            lints: HashMap::new(),
+            error_format: ErrorFormat::Human,
+            uninitialized: Uninitialized::Error,
+            heap: Heap::new(),
+            files: FileTable::new(Box::new(MemoryFileSystem::new())),
+            environment: Box::new(SystemEnvironment::new()),
+            args: vec![],
             scopes: vec![Scope::global()],
+            frames: vec![],
+            call_site: None,
+            blocks: vec![],
+            next_block: 0,
+            exit_point: None,
             // // // stdout: std::io::stdout(),
             // // // stdin: std::io::stdin(),
         }
     }
 
-    pub fn add_var(&mut self, ident: &Identifier, value: Option<&Expression>) {
+    /// Makes words `word_size` bits wide, arithmetic wraps around and
+    /// `printn` writes the bits of a word at that size. Floating point
+    /// numbers need the whole 64 bits of a word
+    pub fn with_word_size(mut self, word_size: u32) -> Result<Self, String> {
+        if !(1..=64).contains(&word_size) {
+            return Err(format!(
+                "A word must be between 1 and 64 bits, not {}",
+                word_size
+            ));
+        }
+        self.word_size = word_size;
+        Ok(self)
+    }
+
+    /// Gives the file builtins access to `filesystem`, by default programs
+    /// only see an empty in-memory one
+    pub fn with_filesystem<F: FileSystem + 'static>(mut self, filesystem: F) -> Self {
+        self.files = FileTable::new(Box::new(filesystem));
+        self
+    }
+
+    /// Replaces where `time`, `clock` and `rand` get their values, by default
+    /// the system clock
+    pub fn with_environment<E: Environment + 'static>(mut self, environment: E) -> Self {
+        self.environment = Box::new(environment);
+        self
+    }
+
+    /// Sets the arguments `main(argc, argv)` receives, conventionally the
+    /// first is the name of the program
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Makes every integer arbitrary precision rather than wrapping at 64 bits
+    pub fn with_big_integers(mut self, big_integers: bool) -> Self {
+        self.big_integers = big_integers;
+        self
+    }
+
+    /// Sets what happens when `lint` finds something in the program, every
+    /// lint warns unless set otherwise
+    pub fn with_lint_level(mut self, lint: Lint, level: Level) -> Self {
+        self.lints.insert(lint, level);
+        self
+    }
+
+    /// Sets how warnings are written out, for errors it is up to whoever
+    /// gets them
+    pub fn with_error_format(mut self, error_format: ErrorFormat) -> Self {
+        self.error_format = error_format;
+        self
+    }
+
+    /// Sets what reading an `auto` that was never assigned gives, by default
+    /// it is an error
+    pub fn with_uninitialized(mut self, uninitialized: Uninitialized) -> Self {
+        self.uninitialized = uninitialized;
+        self
+    }
+
+    pub fn add_var(
+        &mut self,
+        ident: &Identifier,
+        value: Option<&Expression>,
+    ) -> Result<(), RuntimeError> {
         let value = match value {
-            Some(Expression::Constant(c)) => Some(c.clone()),
-            Some(e) => match self.eval_expr(e.clone()) {
-                Expression::Constant(c) => Some(c),
-                _ => panic!("Cannot set variable to a non value expression"),
-            },
+            Some(Expression::Constant { value, .. }) => Some(value.clone()),
+            Some(e) => Some(self.value(e.clone())?),
             None => None,
         };
 
-        self.current_scope().set_var(ident, value.clone());
+        self.current_scope().set_var(ident, value);
+        Ok(())
     }
 
     //TODO
     pub fn add_extern(&mut self, ident: &Identifier) {}
 
-    pub fn call_main(&mut self) -> Const {
-        self.call_function(&Identifier::Name("main".into()), vec![])
-            .expect_const()
-            .unwrap()
+    /// Calls `main` with argc and argv, if the program calls exit() the status
+    /// it exited with is returned in place of `main`'s return value
+    pub fn call_main(&mut self) -> Result<Const, RuntimeError> {
+        let main = Identifier::Name("main".into());
+        let argc = Expression::constant(Const::Integer(self.args.len() as i64));
+        let argv = Expression::constant(Const::Vector(
+            self.args
+                .iter()
+                .map(|arg| Box::new(Expression::constant(Const::String(arg.clone()))))
+                .collect(),
+        ));
+        let args = vec![Box::new(argc), Box::new(argv)];
+
+        match self.call_function(&main, args) {
+            Ok(result) => self.operand(result),
+            Err(e) => match e.kind {
+                ErrorKind::Exit(status) => Ok(Const::Integer(status)),
+                // The block setexit() was called in stops a reset on its way
+                // out, so it can only get here if that block is gone
+                ErrorKind::Reset => Err(RuntimeError::new(
+                    ErrorKind::InvalidReset,
+                    "reset called after the function that called setexit returned",
+                )),
+                _ => Err(e),
+            },
+        }
     }
 
     pub fn call_function(
         &mut self,
         ident: &Identifier,
         arguments: Vec<Box<Expression>>,
-    ) -> Expression {
-        let func = self.get_func(ident).expect("Function not defined");
+    ) -> Result<Expression, RuntimeError> {
+        let func = self.get_func(ident).ok_or_else(|| {
+            RuntimeError::new(
+                ErrorKind::UndefinedFunction,
+                format!("{} is not a function", ident),
+            )
+        })?;
+        let frame = Frame {
+            function: ident.clone(),
+            call_site: self.call_site.take(),
+            arguments: arguments.iter().map(|arg| *arg.clone()).collect(),
+            values: vec![],
+        };
+
         if let Some(builtin) = func.builtin {
-            return builtin(
-                arguments
-                    .into_iter()
-                    .map(|boxed| self.eval_expr(Box::leak(boxed).clone()))
+            let arguments: Vec<Expression> = arguments
+                .into_iter()
+                .map(|boxed| match self.eval_expr(Box::leak(boxed).clone())? {
+                    Expression::Constant {
+                        value: Const::Poison(ident, declared),
+                        ..
+                    } => Err(self.unassigned(&ident, declared)),
+                    argument => Ok(argument),
+                })
+                .collect::<Result<_, _>>()?;
+            self.frames.push(Frame {
+                values: arguments
+                    .iter()
+                    .filter_map(Expression::expect_const)
                     .collect(),
-            );
+                ..frame
+            });
+            let result = builtin(self, arguments).map_err(|e| self.traced(e));
+            self.frames.pop();
+            return result;
         }
 
         // Create a new scope for the function,
+        let values = arguments
+            .into_iter()
+            .map(|arg| self.operand(Box::leak(arg).clone()))
+            .collect::<Result<Vec<_>, _>>()?;
         let mut scope = Scope::new();
-        for (ident, arg) in func.args.iter().zip(arguments.into_iter()) {
-            scope.set_var(ident, self.eval_expr(Box::leak(arg).clone()).expect_const());
+        for (ident, value) in func.args.iter().zip(values.iter()) {
+            scope.set_var(ident, Some(value.clone()));
         }
         self.scopes.push(scope);
+        self.frames.push(Frame { values, ..frame });
 
         // Execute the function in that scope
 
-        let result = self
-            .eval_stmt(func.body.clone())
-            .unwrap_or(Expression::Constant(Const::Integer(0)));
+        let result = self.eval_stmt(func.body.clone()).map_err(|e| match e.kind {
+            // Loops and switches stop a break, so one left them all behind
+            ErrorKind::Break => self.traced(RuntimeError {
+                kind: ErrorKind::InvalidBreak,
+                ..e
+            }),
+            _ => self.traced(e),
+        });
 
+        self.frames.pop();
         self.scopes.pop();
-        result
+        Ok(result?.unwrap_or(Expression::constant(Const::Integer(0))))
+    }
+
+    pub fn source_location(&self, span: Span) -> Option<Location> {
+        self.sources.location(span)
+    }
+
+    /// Every source the program has been loaded from
+    pub fn sources(&self) -> &SourceMap {
+        &self.sources
+    }
+
+    // Points an error at a node unless one inside it already claimed it
+    fn located(&self, error: RuntimeError, span: Option<Span>) -> RuntimeError {
+        match span {
+            Some(span) if !error.kind.unwinds() => error.or_in(span, &self.sources),
+            _ => error,
+        }
+    }
+
+    /// The active calls, innermost first, with the values they were called
+    /// with
+    pub fn trace(&self) -> Vec<TraceFrame> {
+        self.frames
+            .iter()
+            .rev()
+            .map(|frame| TraceFrame {
+                function: frame.function.to_string(),
+                arguments: frame
+                    .values
+                    .iter()
+                    .map(|value| value.render(Some(&self.heap)))
+                    .collect(),
+                call_site: frame.call_site.and_then(|span| self.source_location(span)),
+            })
+            .collect()
+    }
+
+    // Records the calls in progress on an error on its way out of the
+    // innermost one, before they are popped
+    fn traced(&self, mut error: RuntimeError) -> RuntimeError {
+        if error.details.trace.is_empty() && !error.kind.unwinds() {
+            error.details.trace = self.trace();
+        }
+        error
     }
 
     pub fn current_scope(&mut self) -> &mut Scope {
@@ -226,43 +1074,85 @@ impl Interpreter {
         self.current_scope()
             .get_var(ident)
             .or_else(|| self.global_scope().get_var(ident))
-            .map(|constant| Expression::Constant(constant))
+            .map(Expression::constant)
     }
 
     pub fn get_func(&mut self, ident: &Identifier) -> Option<Function> {
         self.global_scope().get_func(ident).cloned()
     }
 
+    /// How `puts` and `format` write a value, strings as they are and
+    /// everything else in full
+    fn show(&self, value: &Const) -> String {
+        match value {
+            Const::String(s) => terminated(s).into(),
+            _ => value.render(Some(&self.heap)),
+        }
+    }
+
+    // Integers that outgrew a word are kept as they are with big integers
+    // enabled, otherwise they wrap around like they would on the machine
+    fn word(&self, value: Const) -> Const {
+        if self.big_integers {
+            value
+        } else {
+            value.wrapped(self.word_size)
+        }
+    }
+
+    // Evaluates an expression that has to come out as a value
+    fn value(&mut self, expr: Expression) -> Result<Const, RuntimeError> {
+        match self.eval_expr(expr)? {
+            Expression::Constant { value, .. } => Ok(value),
+            other => Err(RuntimeError::new(
+                ErrorKind::InvalidOperand,
+                format!("{} is not a value", other),
+            )),
+        }
+    }
+
+    // A value about to be computed with, which a poisoned `auto` cannot be
+    fn operand(&mut self, expr: Expression) -> Result<Const, RuntimeError> {
+        match self.value(expr)? {
+            Const::Poison(ident, declared) => Err(self.unassigned(&ident, declared)),
+            value => Ok(value),
+        }
+    }
+
     fn binary_operation(
         &mut self,
         lhs: Box<Expression>,
         rhs: Box<Expression>,
         operation_function: fn(&Const, &Const) -> Const,
-    ) -> Expression {
-        let lhs = self
-            .eval_expr(Box::leak(lhs).clone())
-            .expect_const()
-            .unwrap_or_else(|| panic!("Binary operation lhs non const value"));
+    ) -> Result<Expression, RuntimeError> {
+        self.checked_operation(lhs, rhs, operation_function, |_| Ok(()))
+    }
 
-        let rhs = self
-            .eval_expr(Box::leak(rhs).clone())
-            .expect_const()
-            .unwrap_or_else(|| panic!("Binary operation rhs non const value"));
+    // A binary operation whose right hand side has to pass `check` first
+    fn checked_operation(
+        &mut self,
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+        operation_function: fn(&Const, &Const) -> Const,
+        check: fn(&Const) -> Result<(), RuntimeError>,
+    ) -> Result<Expression, RuntimeError> {
+        let lhs = self.operand(Box::leak(lhs).clone())?;
+        let rhs = self.operand(Box::leak(rhs).clone())?;
+        check(&rhs)?;
 
-        Expression::Constant(operation_function(&lhs, &rhs))
+        Ok(Expression::constant(
+            self.word(operation_function(&lhs, &rhs)),
+        ))
     }
 
     fn unary_operation(
         &mut self,
         rhs: Box<Expression>,
         operation_function: fn(&Const) -> Const,
-    ) -> Expression {
-        let rhs = self
-            .eval_expr(Box::leak(rhs).clone())
-            .expect_const()
-            .unwrap_or_else(|| panic!("Cannot operate on non const value"));
+    ) -> Result<Expression, RuntimeError> {
+        let rhs = self.operand(Box::leak(rhs).clone())?;
 
-        Expression::Constant(operation_function(&rhs))
+        Ok(Expression::constant(self.word(operation_function(&rhs))))
     }
 
     fn comparison(
@@ -270,14 +1160,13 @@ impl Interpreter {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
         comparison_function: fn(&Expression, &Expression) -> bool,
-    ) -> Expression {
-        if comparison_function(
-            &self.eval_expr(Box::leak(lhs).clone()),
-            &self.eval_expr(Box::leak(rhs).clone()),
-        ) {
-            Expression::Constant(Const::Integer(1))
+    ) -> Result<Expression, RuntimeError> {
+        let lhs = Expression::constant(self.operand(Box::leak(lhs).clone())?);
+        let rhs = Expression::constant(self.operand(Box::leak(rhs).clone())?);
+        if comparison_function(&lhs, &rhs) {
+            Ok(Expression::constant(Const::Integer(1)))
         } else {
-            Expression::Constant(Const::Integer(0))
+            Ok(Expression::constant(Const::Integer(0)))
         }
     }
 
@@ -286,215 +1175,398 @@ impl Interpreter {
         lhs: Box<Expression>,
         rhs: Box<Expression>,
         constructor: fn(Box<Expression>, Box<Expression>) -> Expression,
-    ) -> Expression {
-        match Box::leak(lhs) {
-            Expression::Identifier(ident) => {
-                let value = self.eval_expr(Box::leak(rhs).clone());
-                let current_value = self
-                    .get_var(ident)
-                    .expect(&format!("'{}' is not defined", ident));
-                let value = self.eval_expr(constructor(
-                    Box::new(current_value.clone()),
-                    Box::new(value.clone()),
-                ));
+    ) -> Result<Expression, RuntimeError> {
+        let place = self.place(&lhs)?;
+        let value = self.eval_expr(Box::leak(rhs).clone())?;
+        let current_value = Expression::constant(self.load(&place)?);
+        let value = self.eval_expr(constructor(Box::new(current_value), Box::new(value)))?;
+
+        self.store(&place, &value)?;
+        Ok(value)
+    }
 
-                self.add_var(ident, Some(&value));
-                return value;
+    // Increments or decrements whatever `expr` refers to, giving back the
+    // value from before if `post` and from after otherwise
+    fn step(
+        &mut self,
+        expr: Box<Expression>,
+        operation_function: fn(&Const) -> Const,
+        post: bool,
+    ) -> Result<Expression, RuntimeError> {
+        let expr = Box::leak(expr);
+        if let Expression::Constant { value: c, .. } = expr {
+            return Ok(Expression::constant(self.word(operation_function(c))));
+        }
+
+        let place = self.place(expr)?;
+        let value = match self.load(&place)? {
+            Const::Poison(ident, declared) => return Err(self.unassigned(&ident, declared)),
+            value => value,
+        };
+        let next = self.word(operation_function(&value));
+        self.store(&place, &Expression::constant(next.clone()))?;
+
+        Ok(Expression::constant(if post { value } else { next }))
+    }
+
+    fn place(&mut self, expr: &Expression) -> Result<Place, RuntimeError> {
+        match expr {
+            Expression::Identifier { ident, .. } => Ok(Place::Variable(ident.clone())),
+            Expression::VectorIndex { vector, index, .. } => {
+                let vector = self.operand(*vector.clone())?;
+                let index = self.operand(*index.clone())?;
+                match (vector, index) {
+                    (Const::HeapVector(vector), Const::Integer(index)) => {
+                        Ok(Place::HeapWord(vector, index))
+                    }
+                    (Const::HeapVector(_), index) => Err(RuntimeError::new(
+                        ErrorKind::InvalidOperand,
+                        format!("Cannot index a vector with {}", index),
+                    )),
+                    _ => Err(RuntimeError::new(
+                        ErrorKind::InvalidAssignment,
+                        "Cannot assign into a vector constant, use getvec",
+                    )),
+                }
+            }
+            _ => {
+                let error = RuntimeError::new(
+                    ErrorKind::InvalidAssignment,
+                    format!("Cannot assign to {}", expr),
+                );
+                Err(self.located(error, expr.span()))
             }
-            _ => panic!("Cannot assign to non ident"),
         }
     }
 
-    fn conditional_expr(&mut self, condition: Box<Expression>) -> bool {
-        self.eval_expr(Box::leak(condition).clone())
-            .expect_const()
-            .map(|c| c.truthy())
-            .unwrap_or(/* TODO: FAIL STATE GOES HERE */ false)
+    fn load(&mut self, place: &Place) -> Result<Const, RuntimeError> {
+        match place {
+            Place::Variable(ident) => self.get_const(ident),
+            Place::HeapWord(vector, index) => Ok(self.heap.get(*vector, *index)?),
+        }
     }
 
-    fn get_const(&mut self, ident: &Identifier) -> Const {
-        self.get_var(ident)
-            .unwrap_or_else(|| panic!("{} not defined!", ident))
-            .expect_const()
-            .unwrap_or_else(|| panic!("attempted to get the const value of a non value identifier {} (this should be impossible)", ident))
+    fn store(&mut self, place: &Place, value: &Expression) -> Result<(), RuntimeError> {
+        match place {
+            Place::Variable(ident) => self.add_var(ident, Some(value)),
+            Place::HeapWord(vector, index) => {
+                let value = self.value(value.clone())?;
+                Ok(self.heap.set(*vector, *index, value)?)
+            }
+        }
     }
 
-    pub fn eval_expr(&mut self, expr: Expression) -> Expression {
+    fn conditional_expr(&mut self, condition: Box<Expression>) -> Result<bool, RuntimeError> {
+        Ok(self.operand(Box::leak(condition).clone())?.truthy())
+    }
+
+    fn get_const(&mut self, ident: &Identifier) -> Result<Const, RuntimeError> {
+        if let Some(Expression::Constant { value: c, .. }) = self.get_var(ident) {
+            return Ok(c);
+        }
+        match self.current_scope().unassigned(ident) {
+            Some(declared) => Err(self.unassigned(ident, declared)),
+            None => Err(RuntimeError::new(
+                ErrorKind::UndefinedVariable,
+                format!("{} is not defined", ident),
+            )),
+        }
+    }
+
+    // Reading `ident` before it was given a value, pointing at where it was
+    // declared
+    fn unassigned(&self, ident: &Identifier, declared: Span) -> RuntimeError {
+        RuntimeError::new(
+            ErrorKind::Uninitialized,
+            format!("{} used before assignment", ident),
+        )
+        .with_label(self.source_location(declared), "It is declared here")
+        .with_hint(format!(
+            "Assign {} a value before reading it, an `auto` starts with none",
+            ident
+        ))
+    }
+
+    pub fn eval_expr(&mut self, expr: Expression) -> Result<Expression, RuntimeError> {
+        let span = expr.span();
+        self.eval_node(expr).map_err(|e| self.located(e, span))
+    }
+
+    fn eval_node(&mut self, expr: Expression) -> Result<Expression, RuntimeError> {
         match expr {
-            Expression::Assign { lhs, rhs } => match Box::leak(lhs) {
-                Expression::Identifier(ident) => {
-                    let value = self.eval_expr(Box::leak(rhs).clone());
-                    self.add_var(ident, Some(&value));
-                    return value;
-                }
-                _ => panic!("Cannot assign to non ident"),
-            },
-            Expression::AssignOr { lhs, rhs } => self.assign_and(lhs, rhs, Expression::or),
-            Expression::AssignXor { lhs, rhs } => self.assign_and(lhs, rhs, Expression::xor),
-            Expression::AssignAnd { lhs, rhs } => self.assign_and(lhs, rhs, Expression::and),
-            Expression::AssignShiftLeft { lhs, rhs } => {
+            Expression::Assign { lhs, rhs, .. } => {
+                let place = self.place(&lhs)?;
+                let value = self.eval_expr(Box::leak(rhs).clone())?;
+                self.store(&place, &value)?;
+                Ok(value)
+            }
+            Expression::AssignOr { lhs, rhs, .. } => self.assign_and(lhs, rhs, Expression::or),
+            Expression::AssignXor { lhs, rhs, .. } => self.assign_and(lhs, rhs, Expression::xor),
+            Expression::AssignAnd { lhs, rhs, .. } => self.assign_and(lhs, rhs, Expression::and),
+            Expression::AssignShiftLeft { lhs, rhs, .. } => {
                 self.assign_and(lhs, rhs, Expression::shift_left)
             }
-            Expression::AssignShiftRight { lhs, rhs } => {
+            Expression::AssignShiftRight { lhs, rhs, .. } => {
                 self.assign_and(lhs, rhs, Expression::shift_right)
             }
-            Expression::AssignAdd { lhs, rhs } => self.assign_and(lhs, rhs, Expression::add),
-            Expression::AssignSubtract { lhs, rhs } => {
+            Expression::AssignAdd { lhs, rhs, .. } => self.assign_and(lhs, rhs, Expression::add),
+            Expression::AssignSubtract { lhs, rhs, .. } => {
                 self.assign_and(lhs, rhs, Expression::subtract)
             }
-            Expression::AssignMultiply { lhs, rhs } => {
+            Expression::AssignMultiply { lhs, rhs, .. } => {
                 self.assign_and(lhs, rhs, Expression::multiply)
             }
-            Expression::AssignDivide { lhs, rhs } => self.assign_and(lhs, rhs, Expression::divide),
-            Expression::AssignModulo { lhs, rhs } => self.assign_and(lhs, rhs, Expression::modulo),
-            Expression::Equal { lhs, rhs } => self.comparison(lhs, rhs, Expression::eq),
-            Expression::NotEqual { lhs, rhs } => self.comparison(lhs, rhs, Expression::ne),
-            Expression::Less { lhs, rhs } => self.comparison(lhs, rhs, Expression::lt),
-            Expression::More { lhs, rhs } => self.comparison(lhs, rhs, Expression::gt),
-            Expression::LessEqual { lhs, rhs } => self.comparison(lhs, rhs, Expression::le),
-            Expression::MoreEqual { lhs, rhs } => self.comparison(lhs, rhs, Expression::ge),
-            Expression::Or { lhs, rhs } => self.binary_operation(lhs, rhs, Const::or),
-            Expression::Xor { lhs, rhs } => self.binary_operation(lhs, rhs, Const::xor),
-            Expression::And { lhs, rhs } => self.binary_operation(lhs, rhs, Const::and),
-            Expression::ShiftLeft { lhs, rhs } => self.binary_operation(lhs, rhs, Const::shl),
-            Expression::ShiftRight { lhs, rhs } => self.binary_operation(lhs, rhs, Const::shr),
-            Expression::Add { lhs, rhs } => self.binary_operation(lhs, rhs, Const::add),
-            Expression::Subtract { lhs, rhs } => self.binary_operation(lhs, rhs, Const::sub),
-            Expression::Multiply { lhs, rhs } => self.binary_operation(lhs, rhs, Const::mul),
-            Expression::Divide { lhs, rhs } => self.binary_operation(lhs, rhs, Const::div),
-            Expression::Modulo { lhs, rhs } => self.binary_operation(lhs, rhs, Const::modulo),
-            Expression::Not { rhs } => self.unary_operation(rhs, Const::not),
-            Expression::Complement { rhs } => self.unary_operation(rhs, Const::complement),
-            Expression::UnaryPlus { rhs } => self.eval_expr(Box::leak(rhs).clone()),
-            Expression::UnaryMinus { rhs } => self.unary_operation(rhs, Const::negate),
-            Expression::Ternary { condition, yes, no } => {
-                if self.conditional_expr(condition) {
+            Expression::AssignDivide { lhs, rhs, .. } => {
+                self.assign_and(lhs, rhs, Expression::divide)
+            }
+            Expression::AssignModulo { lhs, rhs, .. } => {
+                self.assign_and(lhs, rhs, Expression::modulo)
+            }
+            Expression::Equal { lhs, rhs, .. } => self.comparison(lhs, rhs, Expression::eq),
+            Expression::NotEqual { lhs, rhs, .. } => self.comparison(lhs, rhs, Expression::ne),
+            Expression::Less { lhs, rhs, .. } => self.comparison(lhs, rhs, Expression::lt),
+            Expression::More { lhs, rhs, .. } => self.comparison(lhs, rhs, Expression::gt),
+            Expression::LessEqual { lhs, rhs, .. } => self.comparison(lhs, rhs, Expression::le),
+            Expression::MoreEqual { lhs, rhs, .. } => self.comparison(lhs, rhs, Expression::ge),
+            Expression::Or { lhs, rhs, .. } => self.binary_operation(lhs, rhs, Const::or),
+            Expression::Xor { lhs, rhs, .. } => self.binary_operation(lhs, rhs, Const::xor),
+            Expression::And { lhs, rhs, .. } => self.binary_operation(lhs, rhs, Const::and),
+            Expression::ShiftLeft { lhs, rhs, .. } if self.big_integers => {
+                self.checked_operation(lhs, rhs, Const::shl, big_shift_count)
+            }
+            Expression::ShiftLeft { lhs, rhs, .. } => {
+                self.checked_operation(lhs, rhs, Const::word_shl, shift_count)
+            }
+            Expression::ShiftRight { lhs, rhs, .. } if self.big_integers => {
+                self.checked_operation(lhs, rhs, Const::shr, big_shift_count)
+            }
+            Expression::ShiftRight { lhs, rhs, .. } => {
+                self.checked_operation(lhs, rhs, Const::word_shr, shift_count)
+            }
+            Expression::Add { lhs, rhs, .. } => self.binary_operation(lhs, rhs, Const::add),
+            Expression::Subtract { lhs, rhs, .. } => self.binary_operation(lhs, rhs, Const::sub),
+            Expression::Multiply { lhs, rhs, .. } => self.binary_operation(lhs, rhs, Const::mul),
+            Expression::Divide { lhs, rhs, .. } => {
+                self.checked_operation(lhs, rhs, Const::div, divisor)
+            }
+            Expression::Modulo { lhs, rhs, .. } => {
+                self.checked_operation(lhs, rhs, Const::modulo, divisor)
+            }
+            Expression::FloatAdd { lhs, rhs, .. } => self.binary_operation(lhs, rhs, Const::fadd),
+            Expression::FloatSubtract { lhs, rhs, .. } => {
+                self.binary_operation(lhs, rhs, Const::fsub)
+            }
+            Expression::FloatMultiply { lhs, rhs, .. } => {
+                self.binary_operation(lhs, rhs, Const::fmul)
+            }
+            Expression::FloatDivide { lhs, rhs, .. } => {
+                self.binary_operation(lhs, rhs, Const::fdiv)
+            }
+            Expression::FloatEqual { lhs, rhs, .. } => self.binary_operation(lhs, rhs, Const::feq),
+            Expression::FloatNotEqual { lhs, rhs, .. } => {
+                self.binary_operation(lhs, rhs, Const::fne)
+            }
+            Expression::FloatLess { lhs, rhs, .. } => self.binary_operation(lhs, rhs, Const::flt),
+            Expression::FloatMore { lhs, rhs, .. } => self.binary_operation(lhs, rhs, Const::fgt),
+            Expression::FloatLessEqual { lhs, rhs, .. } => {
+                self.binary_operation(lhs, rhs, Const::fle)
+            }
+            Expression::FloatMoreEqual { lhs, rhs, .. } => {
+                self.binary_operation(lhs, rhs, Const::fge)
+            }
+            Expression::Not { rhs, .. } => self.unary_operation(rhs, Const::not),
+            Expression::Complement { rhs, .. } => self.unary_operation(rhs, Const::complement),
+            Expression::UnaryPlus { rhs, .. } => self.eval_expr(Box::leak(rhs).clone()),
+            Expression::UnaryMinus { rhs, .. } => self.unary_operation(rhs, Const::negate),
+            Expression::FloatNegate { rhs, .. } => self.unary_operation(rhs, Const::fnegate),
+            Expression::Ternary {
+                condition, yes, no, ..
+            } => {
+                if self.conditional_expr(condition)? {
                     self.eval_expr(Box::leak(yes).clone())
                 } else {
                     self.eval_expr(Box::leak(no).clone())
                 }
             }
-            Expression::PreIncrement { rhs } => match Box::leak(rhs) {
-                Expression::Identifier(ident) => {
-                    let value = Expression::Constant(self.get_const(ident).inc());
-                    self.add_var(ident, Some(&value));
-
-                    value
-                }
-                Expression::Constant(c) => Expression::Constant(c.inc()),
-                _ => panic!("Cannot increment non identifier/constant value"),
-            },
-            Expression::PreDecrement { rhs } => match Box::leak(rhs) {
-                Expression::Identifier(ident) => {
-                    let value = Expression::Constant(self.get_const(ident).dec());
-                    self.add_var(ident, Some(&value));
-
-                    value
-                }
-                Expression::Constant(c) => Expression::Constant(c.dec()),
-                _ => panic!("Cannot decrement non identifier/constant value"),
-            },
-            Expression::PostIncrement { lhs } => match Box::leak(lhs) {
-                Expression::Identifier(ident) => {
-                    let value = self.get_const(ident);
-                    self.add_var(ident, Some(&Expression::Constant(value.inc())));
-
-                    Expression::Constant(value)
-                }
-                Expression::Constant(c) => Expression::Constant(c.inc()),
-                _ => panic!("Cannot increment non identifier/constant value"),
-            },
-            Expression::PostDecrement { lhs } => match Box::leak(lhs) {
-                Expression::Identifier(ident) => {
-                    let value = self.get_const(ident);
-                    self.add_var(ident, Some(&Expression::Constant(value.dec())));
-
-                    Expression::Constant(value)
+            Expression::PreIncrement { rhs, .. } => self.step(rhs, Const::inc, false),
+            Expression::PreDecrement { rhs, .. } => self.step(rhs, Const::dec, false),
+            Expression::PostIncrement { lhs, .. } => self.step(lhs, Const::inc, true),
+            Expression::PostDecrement { lhs, .. } => self.step(lhs, Const::dec, true),
+            Expression::VectorIndex { vector, index, .. } => {
+                let vector = self.operand(Box::leak(vector).clone())?;
+                let index = self.operand(Box::leak(index).clone())?;
+                match (vector, index) {
+                    (Const::HeapVector(vector), Const::Integer(index)) => {
+                        Ok(Expression::constant(self.heap.get(vector, index)?))
+                    }
+                    (Const::Vector(items), Const::Integer(index)) => usize::try_from(index)
+                        .ok()
+                        .and_then(|i| items.get(i))
+                        .map(|item| *item.clone())
+                        .ok_or_else(|| {
+                            RuntimeError::new(
+                                ErrorKind::IndexOutOfBounds,
+                                format!(
+                                    "Index {} out of bounds for vector of {} elements",
+                                    index,
+                                    items.len()
+                                ),
+                            )
+                        }),
+                    (Const::HeapVector(_), index) | (Const::Vector(_), index) => {
+                        Err(RuntimeError::new(
+                            ErrorKind::InvalidOperand,
+                            format!("Cannot index a vector with {}", index),
+                        ))
+                    }
+                    (vector, _) => Err(RuntimeError::new(
+                        ErrorKind::InvalidOperand,
+                        format!("Cannot index {}, it is not a vector", vector),
+                    )),
                 }
-                Expression::Constant(c) => Expression::Constant(c.dec()),
-                _ => panic!("Cannot decrement non identifier/constant value"),
-            },
-            Expression::VectorIndex { vector, index } => {
-                let vector = self
-                    .eval_expr(Box::leak(vector).clone())
-                    .expect_const()
-                    .unwrap_or_else(|| panic!("Cannot index non vector object"));
-                let index = self
-                    .eval_expr(Box::leak(index).clone())
-                    .expect_const()
-                    .unwrap_or_else(|| panic!("Cannot index vector with non constant value"));
-                Box::leak(vector.index(index)).clone()
-            }
-            Expression::Constant(v) => match v {
-                Const::Vector(v) => Expression::Constant(Const::Vector(
+            }
+            Expression::Constant { value: v, .. } => match v {
+                Const::Vector(v) => Ok(Expression::constant(Const::Vector(
                     v.into_iter()
-                        .map(|expr| self.eval_expr(Box::leak(expr).clone()))
-                        .map(Box::new)
-                        .collect(),
-                )),
-                _ => Expression::Constant(v),
+                        .map(|expr| self.eval_expr(Box::leak(expr).clone()).map(Box::new))
+                        .collect::<Result<_, _>>()?,
+                ))),
+                _ => Ok(Expression::constant(self.word(v))),
             },
-            Expression::Identifier(i) => self
-                .get_var(&i)
-                .unwrap_or_else(|| panic!("{} is not defined", i)),
-            Expression::FunctionCall { ident, args } => self.call_function(&ident, args),
+            Expression::Identifier { ident, .. } => {
+                self.get_const(&ident).map(Expression::constant)
+            }
+            Expression::FunctionCall { ident, args, span } => {
+                self.call_site = Some(span);
+                self.call_function(&ident, args)
+            }
         }
     }
 
-    pub fn eval_stmt(&mut self, stmt: Statement) -> Option<Expression> {
+    pub fn eval_stmt(&mut self, stmt: Statement) -> Result<Option<Expression>, RuntimeError> {
+        let span = stmt.span();
+        self.run_stmt(stmt).map_err(|e| self.located(e, Some(span)))
+    }
+
+    fn run_stmt(&mut self, stmt: Statement) -> Result<Option<Expression>, RuntimeError> {
         match stmt {
-            Statement::Compound(stmts) => {
-                for stmt in stmts.into_iter() {
-                    if let Some(return_value) = self.eval_stmt(stmt) {
-                        return Some(return_value);
+            Statement::Compound(stmts, _) => {
+                let block = self.next_block;
+                self.next_block += 1;
+                self.blocks.push((block, 0));
+
+                let mut i = 0;
+                while let Some(stmt) = stmts.get(i) {
+                    self.blocks.last_mut().unwrap().1 = i;
+
+                    // setexit() may be called anywhere in the statement, like
+                    // in the condition of an `if`, so any of them can be reset
+                    // back to. The calls made since have returned by now
+                    let result = self.eval_stmt(stmt.clone());
+                    match (result, self.exit_point) {
+                        (Ok(None), _) => i += 1,
+                        (Err(e), Some(point))
+                            if e.kind == ErrorKind::Reset && point.block == block =>
+                        {
+                            i = point.statement + 1
+                        }
+                        (result, _) => {
+                            self.blocks.pop();
+                            return result;
+                        }
                     }
                 }
-                None
+                self.blocks.pop();
+                Ok(None)
             }
-            Statement::Return(expr) => Some(self.eval_expr(expr)),
-            Statement::Expression(expr) => {
-                self.eval_expr(expr);
-                None
+            Statement::Return(expr, _) => Ok(Some(self.eval_expr(expr)?)),
+            Statement::Expression(expr, _) => {
+                self.eval_expr(expr)?;
+                Ok(None)
             }
 
-            Statement::Declaration { scope, idents } => {
+            Statement::Declaration {
+                scope,
+                idents,
+                span,
+                ..
+            } => {
                 match scope {
                     VariableScope::Extern => idents.iter().for_each(|ident| self.add_extern(ident)),
 
                     VariableScope::Local => {
-                        idents.iter().for_each(|ident| self.add_var(ident, None))
+                        for ident in idents.iter() {
+                            let value = match self.uninitialized {
+                                Uninitialized::Error => None,
+                                Uninitialized::Zero => Some(Const::Integer(0)),
+                                Uninitialized::Poison => Some(Const::Poison(ident.clone(), span)),
+                            };
+                            let scope = self.current_scope();
+                            scope.set_var(ident, value);
+                            scope.declarations.insert(ident.clone(), span);
+                        }
                     }
                 };
-                None
+                Ok(None)
             }
-            Statement::Conditional { condition, body, e } => {
-                if self.conditional_expr(Box::new(condition)) {
+            Statement::Conditional {
+                condition, body, e, ..
+            } => {
+                if self.conditional_expr(Box::new(condition))? {
                     self.eval_stmt(Box::leak(body).clone())
                 } else if let Some(e) = e {
                     self.eval_stmt(Box::leak(e).clone())
                 } else {
-                    None
+                    Ok(None)
                 }
             }
-            Statement::Loop { condition, body } => {
+            Statement::Loop {
+                condition, body, ..
+            } => {
                 let body = Box::leak(body).clone();
                 let condition = Box::new(condition);
-                while self.conditional_expr(condition.clone()) {
-                    if let Some(return_value) = self.eval_stmt(body.clone()) {
-                        return Some(return_value);
+                while self.conditional_expr(condition.clone())? {
+                    match self.eval_stmt(body.clone()) {
+                        Ok(None) => {}
+                        Err(e) if e.kind == ErrorKind::Break => break,
+                        result => return result,
                     }
                 }
-                None
+                Ok(None)
             }
+            // Runs from the matching case on, into the ones after it, until
+            // a break
             Statement::Switch {
                 switching_on,
                 cases,
-            } => None,
+                ..
+            } => {
+                let value = self.operand(switching_on)?;
+                let matching = match cases.iter().position(|case| case.case == value) {
+                    Some(i) => i,
+                    None => return Ok(None),
+                };
+                let stmts = cases[matching..]
+                    .iter()
+                    .flat_map(|case| case.body.iter().flatten());
+                for stmt in stmts {
+                    match self.eval_stmt(stmt.clone()) {
+                        Ok(None) => {}
+                        Err(e) if e.kind == ErrorKind::Break => break,
+                        result => return result,
+                    }
+                }
+                Ok(None)
+            }
             // TODO: Figure out how to do this lol
-            Statement::Label(ident) => unimplemented!(),
-            Statement::Goto(ident) => unimplemented!(),
+            Statement::Label(ident, _) | Statement::Goto(ident, _) => Err(RuntimeError::new(
+                ErrorKind::Unsupported,
+                format!("Labels and goto are not supported yet, found {}", ident),
+            )),
 
-            Statement::FunctionDefinition { ident, args, body } => {
+            Statement::FunctionDefinition {
+                ident, args, body, ..
+            } => {
                 self.global_scope().add_func(
                     ident,
                     Function {
@@ -503,66 +1575,429 @@ impl Interpreter {
                         builtin: None,
                     },
                 );
-                None
+                Ok(None)
             }
             Statement::GlobalDefinition {
                 ident,
                 initial_value,
-            } => None,
+                ..
+            } => Ok(None),
 
-            Statement::Break => None,
-            Statement::Null => None,
+            Statement::Break(span) => Err(RuntimeError::new(
+                ErrorKind::Break,
+                "break outside of a loop or switch",
+            )
+            .or_in(span, &self.sources)),
+            Statement::Error(_) => Err(RuntimeError::new(
+                ErrorKind::Parse,
+                "Cannot run source that did not parse",
+            )),
+            Statement::Null => Ok(None),
         }
     }
 
-    pub fn eval(&mut self, file: Vec<Statement>) {
+    /// Runs the top level of a program, after checking it for mistakes that
+    /// can be found without running it
+    pub fn eval(&mut self, file: Vec<Statement>) -> Result<(), RuntimeError> {
+        let checker = self.scopes[0]
+            .functions
+            .iter()
+            .filter(|(_, function)| function.builtin.is_none())
+            .fold(Checker::new(&self.sources), |checker, (ident, function)| {
+                checker.with_function(ident, Some(function.args.len()))
+            });
+        let mut errors = checker.check(&file);
+        for warning in Linter::new(&self.sources)
+            .with_levels(&self.lints)
+            .lint(&file)
+        {
+            match (warning.level, self.error_format) {
+                (Level::Deny, _) => errors.push(warning.into()),
+                (_, ErrorFormat::Human) => eprintln!("{}\n", warning),
+                (_, ErrorFormat::Json) => eprintln!("{}", Diagnostic::from(&warning).to_json()),
+            }
+        }
+        if let Some(error) = RuntimeError::all(errors) {
+            return Err(error);
+        }
+
         for stmt in file.into_iter() {
-            self.eval_stmt(stmt);
-        }
-    }
-
-    pub fn interpret<P: AsRef<Path>>(mut self, path: P) -> Result<(), String> {
-        let mut buffer = fs::read_to_string(path).unwrap();
-        let mut ast = Parser::new().parse(&buffer).unwrap();
-        //
-        // let s = r#"
-        // main() {
-        //     auto a;
-        //     a = 10;
-        //     return sub5(a);
-        // }
-        //
-        // sub5(a) {
-        //     return a - 5;
-        // }
-        // "#;
-        // let e = crate::grammar::FileParser::new().parse(s).unwrap();
-        println!("Evaluating: {}", buffer);
-        // println!("Expr: {:#?}", ast);
-        self.eval(ast);
-
-        println!(
-            "Result: {:?}",
-            self.call_function(&Identifier::Name("main".into()), vec![])
-                .expect_const()
+            self.eval_stmt(stmt)?;
+        }
+        Ok(())
+    }
+
+    // Adds a source to the ones the program is made of and parses it, failing
+    // with every syntax error in it
+    fn parse<P: ToString>(
+        &mut self,
+        path: P,
+        source: String,
+    ) -> Result<Vec<Statement>, RuntimeError> {
+        self.file = self.sources.add(path, source);
+        let (file, errors) = parse(&self.sources, self.file);
+        match RuntimeError::all(errors) {
+            Some(error) => Err(error),
+            None => Ok(file),
+        }
+    }
+
+    /// Runs the program at `path`, returning the status the process should
+    /// exit with
+    pub fn interpret<P: AsRef<Path>>(mut self, path: P) -> Result<i64, RuntimeError> {
+        let buffer = fs::read_to_string(&path).map_err(|e| {
+            RuntimeError::new(ErrorKind::Io, format!("{}: {}", path.as_ref().display(), e))
+        })?;
+        let ast = self.parse(path.as_ref().display(), buffer)?;
+        self.eval(ast)?;
+
+        let result = self.call_main()?;
+        Ok(match result {
+            Const::Integer(status) => status,
+            _ => 0,
+        })
+    }
+
+    pub fn interpret_string<S: ToString>(mut self, s: S) -> Result<i64, RuntimeError> {
+        let ast = self.parse("<string>", s.to_string())?;
+        self.eval(ast)?;
+
+        let result = self.call_main()?;
+
+        Ok(match result {
+            Const::Integer(status) => status,
+            _ => 0,
+        })
+    }
+}
+
+// Dividing by zero has no result, and neither does dividing by anything
+// falsy since it takes part in arithmetic as 0
+fn divisor(rhs: &Const) -> Result<(), RuntimeError> {
+    if rhs.truthy() {
+        Ok(())
+    } else {
+        Err(RuntimeError::new(
+            ErrorKind::DivisionByZero,
+            "Division by zero",
+        ))
+    }
+}
+
+// The most bits a big integer can be shifted by, past it the result would
+// take more memory than there is
+const MAX_BIG_SHIFT: i64 = 1 << 24;
+
+// Shifting by a negative amount has no result either. A word shifted by 64
+// bits or more has all of them shifted out
+fn shift_count(rhs: &Const) -> Result<(), RuntimeError> {
+    match rhs {
+        Const::Integer(i) if *i < 0 => Err(RuntimeError::new(
+            ErrorKind::InvalidOperand,
+            format!("Cannot shift by a negative amount, {}", i),
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Big integers have no width to shift out of, so they grow with the shift
+fn big_shift_count(rhs: &Const) -> Result<(), RuntimeError> {
+    shift_count(rhs)?;
+    match rhs {
+        Const::Integer(i) if *i <= MAX_BIG_SHIFT => Ok(()),
+        Const::Integer(_) | Const::BigInteger(_) => Err(RuntimeError::new(
+            ErrorKind::InvalidOperand,
+            format!(
+                "Cannot shift by {} bits, big integers can be shifted by at most {}",
+                rhs, MAX_BIG_SHIFT
+            ),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Parses one of `sources`, going on past syntax errors. Gives back as much of
+/// it as parsed, with a `Statement::Error` wherever something did not, and
+/// every error found in the order they are in the source
+pub fn parse(sources: &SourceMap, file: FileId) -> (Vec<Statement>, Vec<RuntimeError>) {
+    let source = &sources
+        .get(file)
+        .expect("file is not in the source map")
+        .source;
+    let located = |e: &ParseError<usize, _, _>| error::parse_error(e, file, sources);
+
+    let mut recovered = vec![];
+    let result = Parser::new().parse(file, &mut recovered, source);
+    let mut errors: Vec<RuntimeError> = recovered
+        .iter()
+        .map(|recovery| located(&recovery.error))
+        .collect();
+    match result {
+        Ok(statements) => (statements, errors),
+        Err(e) => {
+            errors.push(located(&e));
+            (vec![], errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str) -> Result<i64, RuntimeError> {
+        Interpreter::new().interpret_string(source)
+    }
+
+    #[test]
+    fn radixes_write_the_bits_of_a_word_unsigned() {
+        assert_eq!(radix_string(255, 16, 64), "ff");
+        assert_eq!(radix_string(-5, 10, 64), "-5");
+        assert_eq!(radix_string(-1, 8, 16), "177777");
+        assert_eq!(radix_string(-1, 2, 4), "1111");
+        assert_eq!(radix_string(0, 36, 64), "0");
+        assert_eq!(radix_string(35, 36, 64), "z");
+        // Decimal is signed within the word
+        assert_eq!(radix_string(0xffff, 10, 16), "-1");
+    }
+
+    #[test]
+    fn arithmetic_wraps_at_the_word_size() {
+        let run_16 = |source| {
+            Interpreter::new()
+                .with_word_size(16)
                 .unwrap()
+                .interpret_string(source)
+        };
+        assert_eq!(run_16("main() { return 32767 + 1; }").unwrap(), -32768);
+        assert_eq!(run_16("main() { return 256 * 256; }").unwrap(), 0);
+        assert_eq!(run_16("main() { return 65535; }").unwrap(), -1);
+        assert_eq!(
+            run_16("main() { return strcmp(numstr(-1, 8), \"177777\"); }").unwrap(),
+            0
         );
+        assert!(Interpreter::new().with_word_size(0).is_err());
+        assert!(Interpreter::new().with_word_size(65).is_err());
+    }
 
-        Ok(())
+    #[test]
+    fn getvec_too_big_for_memory_is_an_error() {
+        let e = run("main() { getvec(1000000000000); }").unwrap_err();
+        assert_eq!(e.kind, ErrorKind::Heap);
+        assert!(e
+            .message
+            .starts_with("Not enough memory for getvec(1000000000000)"));
     }
 
-    pub fn interpret_string<S: ToString>(mut self, s: S) -> i64 {
-        let ast = Parser::new().parse(&s.to_string()).unwrap();
-        self.eval(ast);
+    #[test]
+    fn numstr_gives_the_text_printn_writes() {
+        let source = "main() {
+            return strcmp(numstr(255, 16), \"ff\") + strcmp(numstr(-8, 8), \"1777777777777777777770\");
+        }";
+        assert_eq!(run(source).unwrap(), 0);
+        for call in [
+            "numstr(\"1\")",
+            "numstr(1, 1)",
+            "numstr(1, 37)",
+            "printn(1, 0)",
+        ] {
+            let source = format!("main() {{ {}; }}", call);
+            assert_eq!(run(&source).unwrap_err().kind, ErrorKind::InvalidArgument);
+        }
+    }
 
-        let result = self.call_main();
+    #[test]
+    fn vector_sizes_must_fit_in_a_word() {
+        let e = run("main() { auto x[99999999999999999999]; return 0; }").unwrap_err();
+        assert_eq!(e.kind, ErrorKind::Parse);
+        assert_eq!(e.message, "Vector size does not fit in a word");
+        let location = e.location.unwrap();
+        assert_eq!((location.line, location.column), (1, 17));
+        assert_eq!((location.end_line, location.end_column), (1, 37));
+    }
 
-        match result {
-            Const::Integer(i) => i,
-            other => {
-                println!("Result: {:?}", other);
-                return 0;
+    #[test]
+    fn dividing_by_anything_falsy_is_an_error() {
+        for source in [
+            "main() { return 5 / 0; }",
+            "main() { return 5 % \"\"; }",
+            "main() { auto x; x = 5; x =% 0; return x; }",
+        ] {
+            assert_eq!(run(source).unwrap_err().kind, ErrorKind::DivisionByZero);
+        }
+        assert_eq!(run("main() { return 5 % \"a\"; }").unwrap(), 0);
+    }
+
+    #[test]
+    fn assigning_to_a_literal_points_at_it() {
+        for source in ["main() {\n    1 = 2;\n}", "main() {\n    5 =+ 1;\n}"] {
+            let e = run(source).unwrap_err();
+            assert_eq!(e.kind, ErrorKind::InvalidAssignment);
+            let location = e.location.unwrap();
+            assert_eq!((location.line, location.column), (2, 5));
+            assert_eq!(e.details.snippet.unwrap().lines().last(), Some("  |     ^"));
+        }
+    }
+
+    #[test]
+    fn funcs_lists_the_program_functions_then_a_zero() {
+        let source = "
+            test_x() { return 0; }
+            f() { return 0; }
+            main() {
+                auto names, n;
+                names = funcs();
+                n = 0;
+                while (names[n]) { n++; }
+                return n * 100 + strcmp(names[0], \"f\") + strcmp(names[2], \"test_x\");
+            }";
+        assert_eq!(run(source).unwrap(), 300);
+        let e = run("main() { return funcs()[2]; }").unwrap_err();
+        assert_eq!(e.kind, ErrorKind::IndexOutOfBounds);
+    }
+
+    #[test]
+    fn exit_leaves_every_call_with_its_status() {
+        let source = "
+            f(n) { if (n == 0) { exit(7); } return f(n - 1); }
+            main() { f(10); return 1; }";
+        assert_eq!(run(source).unwrap(), 7);
+        assert_eq!(run("main() { exit(); return 1; }").unwrap(), 0);
+    }
+
+    #[test]
+    fn failed_assertions_are_errors_showing_the_condition() {
+        assert_eq!(run("main() { assert(1 == 1); return 2; }").unwrap(), 2);
+        let e = run("main() { auto x; x = 3; assert(x*2  ==  7, \"doubling\"); }").unwrap_err();
+        assert_eq!(e.kind, ErrorKind::AssertionFailed);
+        assert_eq!(e.message, "Assertion failed: x*2  ==  7, doubling");
+        assert_eq!(e.location.map(|location| location.line), Some(1));
+        assert_eq!(e.details.trace.len(), 2);
+    }
+
+    #[test]
+    fn reset_continues_after_the_statement_that_called_setexit() {
+        let source = "
+            f(n) { if (n > 0) { f(n - 1); } reset(); }
+            main() {
+                auto tries;
+                tries = 0;
+                setexit();
+                tries++;
+                if (tries < 3) { f(5); }
+                return tries;
+            }";
+        assert_eq!(run(source).unwrap(), 3);
+    }
+
+    #[test]
+    fn setexit_can_be_part_of_a_bigger_statement() {
+        let source = "
+            f() { reset(); return 1; }
+            main() {
+                auto n;
+                n = 10;
+                if (setexit() == 0) { n++; f(); n = 100; }
+                return n;
+            }";
+        assert_eq!(run(source).unwrap(), 11);
+    }
+
+    #[test]
+    fn reset_without_setexit_is_an_error() {
+        let e = run("main() { reset(); }").unwrap_err();
+        assert_eq!(e.kind, ErrorKind::InvalidReset);
+        let e = run("f() { setexit(); } main() { f(); reset(); }").unwrap_err();
+        assert_eq!(e.kind, ErrorKind::InvalidReset);
+    }
+
+    #[test]
+    fn switch_runs_from_the_matching_case_until_a_break() {
+        let source = "
+            f(n) {
+                auto x;
+                x = 0;
+                switch (n) {
+                case 1: x = x + 1;
+                case 2: x = x + 10; break;
+                case 3: x = 100;
+                }
+                return x;
             }
+            main() { return f(1) * 10000 + f(2) * 100 + f(3) + f(4); }";
+        assert_eq!(run(source).unwrap(), 11 * 10000 + 10 * 100 + 100);
+    }
+
+    #[test]
+    fn break_leaves_the_innermost_loop() {
+        let source = "
+            main() {
+                auto i, n;
+                i = n = 0;
+                while (1) {
+                    switch (i) { case 2: n = n + 100; break; }
+                    if (i == 3) { break; }
+                    i++;
+                    n++;
+                }
+                return n;
+            }";
+        assert_eq!(run(source).unwrap(), 103);
+        let e = run("main() { break; }").unwrap_err();
+        assert_eq!(e.kind, ErrorKind::InvalidBreak);
+        assert_eq!(e.location.map(|location| location.column), Some(10));
+    }
+
+    fn run_with(uninitialized: Uninitialized, source: &str) -> Result<i64, RuntimeError> {
+        Interpreter::new()
+            .with_uninitialized(uninitialized)
+            .interpret_string(source)
+    }
+
+    fn assert_unassigned(result: Result<i64, RuntimeError>) {
+        let e = result.unwrap_err();
+        assert_eq!(e.kind, ErrorKind::Uninitialized);
+        assert_eq!(e.message, "x used before assignment");
+        let label = &e.details.labels[0];
+        assert_eq!(label.message, "It is declared here");
+        assert_eq!(label.location.line, 2);
+    }
+
+    #[test]
+    fn reading_an_unassigned_auto_is_an_error_by_default() {
+        assert_unassigned(run("main() {\n auto x;\n return x; }"));
+        assert_eq!(run("main() { auto x; x = 4; return x; }").unwrap(), 4);
+    }
+
+    #[test]
+    fn unassigned_autos_can_start_at_zero() {
+        let source = "main() { auto x, y; y = x + 3; return y; }";
+        assert_eq!(run_with(Uninitialized::Zero, source).unwrap(), 3);
+    }
+
+    #[test]
+    fn poisoned_autos_can_be_copied_but_not_used() {
+        let copied = "main() { auto x, y; y = x; x = 2; return x; }";
+        assert_eq!(run_with(Uninitialized::Poison, copied).unwrap(), 2);
+        for used in [
+            "x * 2",
+            "-x",
+            "x == 0",
+            "x ? 1 : 2",
+            "x++",
+            "\"ab\"[x]",
+            "printn(x, 10)",
+            "f(x)",
+        ] {
+            let source = format!(
+                "f(n) {{ return n; }}\nmain() {{ auto x;\n return {}; }}",
+                used
+            );
+            assert_unassigned(run_with(Uninitialized::Poison, &source));
         }
+        let condition = "main() {\n auto x;\n if (x) { return 1; } return 2; }";
+        assert_unassigned(run_with(Uninitialized::Poison, condition));
+        let passed_on = "f() {\n auto x;\n return x; }\nmain() { return f(); }";
+        assert_unassigned(run_with(Uninitialized::Poison, passed_on));
     }
 }
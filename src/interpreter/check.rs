@@ -0,0 +1,404 @@
+//! Mistakes that can be found in a program without running it: calls to
+//! functions that do not exist or with the wrong number of arguments, names
+//! that are never declared, a `goto` with no label to go to and `case`s that
+//! repeat an earlier one.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{ErrorKind, RuntimeError, Scope};
+use crate::{
+    ast::{CaseStatement, Const, Expression, Identifier, Statement},
+    source::{SourceMap, Span},
+};
+
+/// Checks a parsed program before it runs, `check` does it with just the
+/// builtins known
+pub struct Checker<'a> {
+    sources: &'a SourceMap,
+    // Everything that can be called, with how many arguments it takes. None
+    // for builtins, which take any number
+    functions: HashMap<String, Option<usize>>,
+    // Names declared outside any function
+    globals: HashSet<String>,
+    // Names in the program's strings when it calls eval, which could be
+    // functions it defines as it goes
+    evaluated: HashSet<String>,
+    errors: Vec<RuntimeError>,
+}
+
+impl<'a> Checker<'a> {
+    pub fn new(sources: &'a SourceMap) -> Self {
+        let functions = Scope::global()
+            .functions
+            .keys()
+            .map(|ident| (ident.to_string(), None))
+            .collect();
+        Self {
+            sources,
+            functions,
+            globals: HashSet::new(),
+            evaluated: HashSet::new(),
+            errors: vec![],
+        }
+    }
+
+    /// Makes a function defined elsewhere, like by an earlier `eval`, known
+    pub fn with_function<S: ToString>(mut self, name: S, arguments: Option<usize>) -> Self {
+        self.functions.insert(name.to_string(), arguments);
+        self
+    }
+
+    /// Every mistake found in `file`, in the order they are in the source
+    pub fn check(mut self, file: &[Statement]) -> Vec<RuntimeError> {
+        for stmt in file {
+            match stmt {
+                Statement::FunctionDefinition { ident, args, .. } => {
+                    self.functions.insert(ident.to_string(), Some(args.len()));
+                }
+                Statement::GlobalDefinition { ident, .. } => {
+                    self.globals.insert(ident.to_string());
+                }
+                _ => {}
+            }
+        }
+        // A program that evals source can define functions as it goes, so
+        // calls to ones named in its strings may be fine
+        let mut calls_eval = false;
+        let mut strings = vec![];
+        for stmt in file {
+            if let Statement::GlobalDefinition {
+                initial_value: Const::String(s),
+                ..
+            } = stmt
+            {
+                strings.push(s);
+            }
+            visit_expressions(stmt, &mut |expr| match expr {
+                Expression::FunctionCall { ident, .. } => calls_eval |= ident.to_string() == "eval",
+                Expression::Constant {
+                    value: Const::String(s),
+                    ..
+                } => strings.push(s),
+                _ => {}
+            });
+        }
+        if calls_eval {
+            self.evaluated = strings
+                .iter()
+                .flat_map(|s| s.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_')))
+                .map(str::to_string)
+                .collect();
+        }
+
+        for stmt in file {
+            if let Statement::FunctionDefinition { args, body, .. } = stmt {
+                let mut function = FunctionScope {
+                    variables: args.iter().map(Identifier::to_string).collect(),
+                    labels: HashSet::new(),
+                };
+                function.declare(body);
+                self.statement(&function, body);
+            }
+        }
+        self.errors
+    }
+
+    fn error(&mut self, kind: ErrorKind, message: String, span: Span) -> &mut RuntimeError {
+        let error = RuntimeError::new(kind, message).or_in(span, self.sources);
+        self.errors.push(error);
+        self.errors.last_mut().unwrap()
+    }
+
+    fn statement(&mut self, function: &FunctionScope, stmt: &Statement) {
+        match stmt {
+            Statement::Compound(stmts, _) => {
+                stmts.iter().for_each(|stmt| self.statement(function, stmt))
+            }
+            Statement::Return(expr, _) | Statement::Expression(expr, _) => {
+                self.expression(function, expr)
+            }
+            Statement::Conditional {
+                condition, body, e, ..
+            } => {
+                self.expression(function, condition);
+                self.statement(function, body);
+                if let Some(e) = e {
+                    self.statement(function, e);
+                }
+            }
+            Statement::Loop {
+                condition, body, ..
+            } => {
+                self.expression(function, condition);
+                self.statement(function, body);
+            }
+            Statement::Switch {
+                switching_on,
+                cases,
+                ..
+            } => {
+                self.expression(function, switching_on);
+                self.cases(cases);
+                for stmt in cases.iter().flat_map(|case| case.body.iter().flatten()) {
+                    self.statement(function, stmt);
+                }
+            }
+            Statement::Goto(label, span) => {
+                if !function.labels.contains(&label.to_string()) {
+                    self.error(
+                        ErrorKind::UndefinedLabel,
+                        format!("There is no label {} in this function to go to", label),
+                        *span,
+                    );
+                }
+            }
+            Statement::Label(..)
+            | Statement::Declaration { .. }
+            | Statement::FunctionDefinition { .. }
+            | Statement::GlobalDefinition { .. }
+            | Statement::Break(_)
+            | Statement::Error(_)
+            | Statement::Null => {}
+        }
+    }
+
+    fn cases(&mut self, cases: &[CaseStatement]) {
+        for (i, case) in cases.iter().enumerate() {
+            let first = cases[..i].iter().find(|earlier| earlier.case == case.case);
+            if let Some(first) = first {
+                let error = RuntimeError::new(
+                    ErrorKind::DuplicateCase,
+                    format!("case {} is handled twice in this switch", case.case),
+                )
+                .or_in(case.span, self.sources)
+                .with_label(
+                    self.sources.location(first.span),
+                    "It is first handled here",
+                );
+                self.errors.push(error);
+            }
+        }
+    }
+
+    fn expression(&mut self, function: &FunctionScope, expr: &Expression) {
+        match expr {
+            Expression::Identifier { ident, span } => {
+                let name = ident.to_string();
+                if !function.variables.contains(&name) && !self.globals.contains(&name) {
+                    self.error(
+                        ErrorKind::UndefinedVariable,
+                        format!("{} is not declared", ident),
+                        *span,
+                    );
+                }
+            }
+            Expression::FunctionCall { ident, args, span } => {
+                match self.functions.get(&ident.to_string()) {
+                    Some(Some(expected)) if *expected != args.len() => {
+                        let expected = *expected;
+                        self.error(
+                            ErrorKind::WrongArgumentCount,
+                            format!(
+                                "{} takes {} argument{} but {} {} given",
+                                ident,
+                                expected,
+                                if expected == 1 { "" } else { "s" },
+                                args.len(),
+                                if args.len() == 1 { "was" } else { "were" }
+                            ),
+                            *span,
+                        );
+                    }
+                    Some(_) => {}
+                    None if self.evaluated.contains(&ident.to_string()) => {}
+                    None => {
+                        self.error(
+                            ErrorKind::UndefinedFunction,
+                            format!("{} is not a function", ident),
+                            *span,
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+        for operand in expr.operands() {
+            self.expression(function, operand);
+        }
+    }
+}
+
+/// Every mistake in `file` that can be found without running it
+pub fn check(file: &[Statement], sources: &SourceMap) -> Vec<RuntimeError> {
+    Checker::new(sources).check(file)
+}
+
+// What is declared in the function being checked. Autos count from anywhere
+// in the function, like the scope the interpreter gives them
+struct FunctionScope {
+    variables: HashSet<String>,
+    labels: HashSet<String>,
+}
+impl FunctionScope {
+    fn declare(&mut self, stmt: &Statement) {
+        match stmt {
+            // Externs name globals, which may come from another file
+            Statement::Declaration { idents, .. } => {
+                self.variables
+                    .extend(idents.iter().map(Identifier::to_string));
+            }
+            Statement::Label(label, _) => {
+                self.labels.insert(label.to_string());
+            }
+            _ => {}
+        }
+        for stmt in statements(stmt) {
+            self.declare(stmt);
+        }
+    }
+}
+
+// The statements directly inside one
+pub(super) fn statements(stmt: &Statement) -> Vec<&Statement> {
+    match stmt {
+        Statement::Compound(stmts, _) => stmts.iter().collect(),
+        Statement::Conditional { body, e, .. } => {
+            std::iter::once(&**body).chain(e.as_deref()).collect()
+        }
+        Statement::Loop { body, .. } | Statement::FunctionDefinition { body, .. } => vec![body],
+        Statement::Switch { cases, .. } => cases
+            .iter()
+            .flat_map(|case| case.body.iter().flatten())
+            .collect(),
+        _ => vec![],
+    }
+}
+
+// The expression a statement works on itself, not counting the statements
+// inside it
+pub(super) fn expression(stmt: &Statement) -> Option<&Expression> {
+    match stmt {
+        Statement::Return(expr, _)
+        | Statement::Expression(expr, _)
+        | Statement::Conditional {
+            condition: expr, ..
+        }
+        | Statement::Loop {
+            condition: expr, ..
+        }
+        | Statement::Switch {
+            switching_on: expr, ..
+        } => Some(expr),
+        _ => None,
+    }
+}
+
+// Calls `visit` on an expression and every one inside it
+pub(super) fn visit_expression<'s>(expr: &'s Expression, visit: &mut impl FnMut(&'s Expression)) {
+    visit(expr);
+    for operand in expr.operands() {
+        visit_expression(operand, visit);
+    }
+}
+
+// Calls `visit` on every expression in a statement and the ones inside it
+pub(super) fn visit_expressions<'s>(stmt: &'s Statement, visit: &mut impl FnMut(&'s Expression)) {
+    if let Some(expr) = expression(stmt) {
+        visit_expression(expr, visit);
+    }
+    for stmt in statements(stmt) {
+        visit_expressions(stmt, visit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::parse;
+
+    fn kinds(source: &str) -> Vec<(ErrorKind, String)> {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.b", source);
+        let (program, errors) = parse(&sources, file);
+        assert!(errors.is_empty(), "{:?}", errors);
+        check(&program, &sources)
+            .into_iter()
+            .map(|e| (e.kind, e.message))
+            .collect()
+    }
+
+    #[test]
+    fn calls_need_a_function_with_that_many_arguments() {
+        assert_eq!(
+            kinds("f(a, b) { return a + b; } main() { f(1); g(); f(1, 2); }"),
+            vec![
+                (
+                    ErrorKind::WrongArgumentCount,
+                    "f takes 2 arguments but 1 was given".to_string()
+                ),
+                (ErrorKind::UndefinedFunction, "g is not a function".into()),
+            ]
+        );
+        // Builtins take any number
+        assert!(kinds("main() { printn(1); printn(1, 10); }").is_empty());
+    }
+
+    #[test]
+    fn names_need_declaring_somewhere() {
+        let source = "
+            g 5;
+            main(argc) {
+                extrn h;
+                auto x;
+                x = argc + g + h;
+                if (x) { auto y; y = 1; }
+                return y + z;
+            }";
+        assert_eq!(
+            kinds(source),
+            vec![(ErrorKind::UndefinedVariable, "z is not declared".into())]
+        );
+    }
+
+    #[test]
+    fn programs_that_eval_may_define_functions_later() {
+        assert!(kinds("main() { eval(\"f() { return 1; }\"); return f(); }").is_empty());
+        let source = "
+            code \"g(x) { return x; }\";
+            main() { eval(code); return f() + g(1); }";
+        assert_eq!(
+            kinds(source),
+            vec![(ErrorKind::UndefinedFunction, "f is not a function".into())]
+        );
+    }
+
+    #[test]
+    fn cases_cannot_repeat() {
+        let mut sources = SourceMap::new();
+        let file = sources.add(
+            "test.b",
+            "main() { switch (1) { case 1: case 2: break; case 1: break; } }",
+        );
+        let (program, _) = parse(&sources, file);
+        let errors = check(&program, &sources);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ErrorKind::DuplicateCase);
+        assert_eq!(errors[0].message, "case 1 is handled twice in this switch");
+        assert_eq!(errors[0].location.as_ref().unwrap().column, 46);
+        assert_eq!(errors[0].details.labels[0].location.column, 23);
+    }
+
+    #[test]
+    fn gotos_need_a_label_in_their_function() {
+        let source = "
+            f() { goto done; done: return 1; }
+            main() { goto done; return f(); }";
+        assert_eq!(
+            kinds(source),
+            vec![(
+                ErrorKind::UndefinedLabel,
+                "There is no label done in this function to go to".into()
+            )]
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Errors and warnings in a form for tools like editors rather than people,
+//! written out as one line of JSON each.
+
+use std::{fmt::Write, str::FromStr};
+
+use super::{Fix, Label, Level, Location, RuntimeError, Warning};
+
+/// How errors and warnings are written out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// With source snippets, for reading
+    Human,
+    /// A line of JSON for each diagnostic
+    Json,
+}
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!("There is no error format called {}", name)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// One error or warning, with everything that goes with it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// What kind it is, like `division-by-zero` or a lint's name
+    pub code: String,
+    pub message: String,
+    /// The span it is about, None if it is not about one place in the source
+    pub location: Option<Location>,
+    pub labels: Vec<Label>,
+    pub fixes: Vec<Fix>,
+    pub hints: Vec<String>,
+}
+
+impl Diagnostic {
+    /// It as a single line of JSON, like
+    ///
+    /// ```text
+    /// {"severity":"error","code":"parse","message":"...","span":{...},
+    ///  "labels":[],"fixes":[],"hints":[]}
+    /// ```
+    ///
+    /// Spans have the file, the byte range and the lines and columns it
+    /// starts and ends at, counted from 1.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        write!(
+            json,
+            "{{\"severity\":{},\"code\":{},\"message\":{},\"span\":{},\"labels\":[",
+            string(self.severity.name()),
+            string(&self.code),
+            string(&self.message),
+            self.location.as_ref().map_or("null".into(), span),
+        )
+        .unwrap();
+        let labels: Vec<_> = self
+            .labels
+            .iter()
+            .map(|label| {
+                format!(
+                    "{{\"message\":{},\"span\":{}}}",
+                    string(&label.message),
+                    span(&label.location)
+                )
+            })
+            .collect();
+        json.push_str(&labels.join(","));
+        json.push_str("],\"fixes\":[");
+        let fixes: Vec<_> = self
+            .fixes
+            .iter()
+            .map(|fix| {
+                format!(
+                    "{{\"message\":{},\"span\":{},\"replacement\":{}}}",
+                    string(&fix.message),
+                    span(&fix.location),
+                    string(&fix.replacement)
+                )
+            })
+            .collect();
+        json.push_str(&fixes.join(","));
+        json.push_str("],\"hints\":[");
+        let hints: Vec<_> = self.hints.iter().map(|hint| string(hint)).collect();
+        json.push_str(&hints.join(","));
+        json.push_str("]}");
+        json
+    }
+}
+
+impl From<&Warning> for Diagnostic {
+    fn from(warning: &Warning) -> Self {
+        Self {
+            severity: match warning.level {
+                Level::Deny => Severity::Error,
+                Level::Allow | Level::Warn => Severity::Warning,
+            },
+            code: warning.lint.name().to_string(),
+            message: warning.message.clone(),
+            location: warning.location.clone(),
+            labels: warning.details.labels.clone(),
+            fixes: warning.details.fixes.clone(),
+            hints: warning.details.hints.clone(),
+        }
+    }
+}
+
+impl RuntimeError {
+    /// It and every error that came with it, the calls it happened in
+    /// becoming labels on their call sites
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut labels = self.details.labels.clone();
+        labels.extend(self.details.trace.iter().filter_map(|frame| {
+            Some(Label {
+                location: frame.call_site.clone()?,
+                message: format!(
+                    "{}({}) called here",
+                    frame.function,
+                    frame.arguments.join(", ")
+                ),
+            })
+        }));
+        let diagnostic = Diagnostic {
+            severity: Severity::Error,
+            code: self.kind.code().to_string(),
+            message: self.message.clone(),
+            location: self.location.clone(),
+            labels,
+            fixes: self.details.fixes.clone(),
+            hints: self.details.hints.clone(),
+        };
+        std::iter::once(diagnostic)
+            .chain(
+                self.details
+                    .others
+                    .iter()
+                    .flat_map(RuntimeError::diagnostics),
+            )
+            .collect()
+    }
+}
+
+fn span(location: &Location) -> String {
+    format!(
+        "{{\"file\":{},\"start\":{},\"end\":{},\"line\":{},\"column\":{},\
+         \"end_line\":{},\"end_column\":{}}}",
+        string(&location.path),
+        location.start,
+        location.end,
+        location.line,
+        location.column,
+        location.end_line,
+        location.end_column
+    )
+}
+
+// A JSON string, quoted and escaped
+fn string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::ErrorKind;
+
+    fn location(line: usize) -> Location {
+        Location {
+            path: "dir\\a \"b\".b".into(),
+            line,
+            column: 2,
+            end_line: line,
+            end_column: 5,
+            start: 10,
+            end: 13,
+        }
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        assert_eq!(
+            string("say \"hi\"\\\n\r\t\u{1}é"),
+            r#""say \"hi\"\\\n\r\t\u0001é""#
+        );
+    }
+
+    #[test]
+    fn diagnostics_are_one_line_of_json() {
+        let diagnostic = Diagnostic {
+            severity: Severity::Warning,
+            code: "assign-minus".into(),
+            message: "`=-`\nsubtracts".into(),
+            location: Some(location(3)),
+            labels: vec![Label {
+                location: location(1),
+                message: "here".into(),
+            }],
+            fixes: vec![Fix {
+                message: "Add a \"break;\"".into(),
+                location: location(2),
+                replacement: " break;".into(),
+            }],
+            hints: vec!["a\tb".into(), "c".into()],
+        };
+        let span = |line| {
+            format!(
+                r#"{{"file":"dir\\a \"b\".b","start":10,"end":13,"line":{0},"column":2,"end_line":{0},"end_column":5}}"#,
+                line
+            )
+        };
+        assert_eq!(
+            diagnostic.to_json(),
+            format!(
+                r#"{{"severity":"warning","code":"assign-minus","message":"`=-`\nsubtracts","span":{},"labels":[{{"message":"here","span":{}}}],"fixes":[{{"message":"Add a \"break;\"","span":{},"replacement":" break;"}}],"hints":["a\tb","c"]}}"#,
+                span(3),
+                span(1),
+                span(2)
+            )
+        );
+    }
+
+    #[test]
+    fn errors_without_a_location_have_a_null_span() {
+        let error = RuntimeError::new(ErrorKind::Io, "gone");
+        let diagnostics = error.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_json(),
+            r#"{"severity":"error","code":"io","message":"gone","span":null,"labels":[],"fixes":[],"hints":[]}"#
+        );
+    }
+}
@@ -0,0 +1,529 @@
+use std::{
+    collections::BTreeSet,
+    fmt::{Display, Formatter},
+};
+
+use lalrpop_util::ParseError;
+
+use super::Lint;
+pub use crate::source::Location;
+use crate::{
+    heap::HeapError,
+    source::{FileId, SourceMap, Span},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The program could not be read
+    Io,
+    Parse,
+    UndefinedFunction,
+    UndefinedVariable,
+    /// Reading an `auto` before anything was assigned to it
+    Uninitialized,
+    /// A `goto` to a label the function does not have
+    UndefinedLabel,
+    /// A call to a function with more or fewer arguments than it takes
+    WrongArgumentCount,
+    /// A `case` with the same value as an earlier one in its `switch`
+    DuplicateCase,
+    /// Something found by a lint set to deny
+    Lint(Lint),
+    /// Assigning to something that is not a variable or a vector word
+    InvalidAssignment,
+    /// An operator or index applied to a value it does not work on
+    InvalidOperand,
+    DivisionByZero,
+    IndexOutOfBounds,
+    /// Misuse of a vector from `getvec`, like using it after releasing it
+    Heap,
+    /// A builtin called with arguments it does not accept
+    InvalidArgument,
+    /// `reset()` with nowhere to return to
+    InvalidReset,
+    /// `break` outside of a loop or `switch`
+    InvalidBreak,
+    /// An `assert` whose condition did not hold
+    AssertionFailed,
+    Unsupported,
+    /// A bug in the interpreter rather than the program
+    Internal,
+    /// Not an error, `exit(status)` carrying the program out of every call
+    Exit(i64),
+    /// Not an error, `reset()` carrying the program back to its `setexit()`
+    Reset,
+    /// Not an error, `break` carrying the program out of its loop or `switch`
+    Break,
+}
+impl ErrorKind {
+    /// Whether it is `exit`, `reset` or `break` passing through rather than
+    /// an error, so there is nothing to report about where it went
+    pub fn unwinds(self) -> bool {
+        matches!(
+            self,
+            ErrorKind::Exit(_) | ErrorKind::Reset | ErrorKind::Break
+        )
+    }
+
+    /// A name for the kind of error that tools can match on, the lint's name
+    /// for lints
+    pub fn code(self) -> &'static str {
+        match self {
+            ErrorKind::Io => "io",
+            ErrorKind::Parse => "parse",
+            ErrorKind::UndefinedFunction => "undefined-function",
+            ErrorKind::UndefinedVariable => "undefined-variable",
+            ErrorKind::Uninitialized => "uninitialized",
+            ErrorKind::UndefinedLabel => "undefined-label",
+            ErrorKind::WrongArgumentCount => "wrong-argument-count",
+            ErrorKind::DuplicateCase => "duplicate-case",
+            ErrorKind::Lint(lint) => lint.name(),
+            ErrorKind::InvalidAssignment => "invalid-assignment",
+            ErrorKind::InvalidOperand => "invalid-operand",
+            ErrorKind::DivisionByZero => "division-by-zero",
+            ErrorKind::IndexOutOfBounds => "index-out-of-bounds",
+            ErrorKind::Heap => "heap",
+            ErrorKind::InvalidArgument => "invalid-argument",
+            ErrorKind::InvalidReset => "invalid-reset",
+            ErrorKind::InvalidBreak => "invalid-break",
+            ErrorKind::AssertionFailed => "assertion-failed",
+            ErrorKind::Unsupported => "unsupported",
+            ErrorKind::Internal => "internal",
+            ErrorKind::Exit(_) => "exit",
+            ErrorKind::Reset => "reset",
+            ErrorKind::Break => "break",
+        }
+    }
+}
+
+// How many calls are shown from each end of a long call stack
+const TRACE_ENDS: usize = 10;
+
+/// Why running a program failed, and where if it is known
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub location: Option<Location>,
+    // Boxed so results carrying an error stay small
+    pub details: Box<Details>,
+}
+
+/// What else is known about a `RuntimeError`, for showing to a person
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Details {
+    /// The source line at the location with the offending part underlined
+    pub snippet: Option<String>,
+    /// Suggestions for fixing it, like a `;` that looks to be missing
+    pub hints: Vec<String>,
+    /// Other places in the source that have to do with it
+    pub labels: Vec<Label>,
+    /// Changes to the source that would fix it
+    pub fixes: Vec<Fix>,
+    /// The calls in progress when it happened, innermost first
+    pub trace: Vec<TraceFrame>,
+    /// More errors found along with this one, like the rest of the syntax
+    /// errors in a file
+    pub others: Vec<RuntimeError>,
+}
+
+/// Another place in the source an error or warning has to do with, like the
+/// first of two `case`s with the same value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub location: Location,
+    pub message: String,
+}
+
+/// A change to the source that would fix an error or warning, replacing what
+/// is at the location
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fix {
+    pub message: String,
+    pub location: Location,
+    pub replacement: String,
+}
+
+/// A call that was in progress when an error happened
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFrame {
+    pub function: String,
+    /// The values it was called with, written out the way `dump` does
+    pub arguments: Vec<String>,
+    /// Where it was called from, None for a call the host made like `main`
+    pub call_site: Option<Location>,
+}
+impl Display for TraceFrame {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}({})", self.function, self.arguments.join(", "))?;
+        if let Some(location) = &self.call_site {
+            write!(f, " at {}", location)?;
+        }
+        Ok(())
+    }
+}
+
+impl RuntimeError {
+    pub fn new<S: ToString>(kind: ErrorKind, message: S) -> Self {
+        Self {
+            kind,
+            message: message.to_string(),
+            location: None,
+            details: Box::default(),
+        }
+    }
+
+    /// The first of `errors` with the rest as its others, None if there are
+    /// none
+    pub fn all(mut errors: Vec<RuntimeError>) -> Option<Self> {
+        if errors.is_empty() {
+            return None;
+        }
+        let mut first = errors.remove(0);
+        first.details.others.extend(errors);
+        Some(first)
+    }
+
+    pub fn with_hint<S: ToString>(mut self, hint: S) -> Self {
+        self.details.hints.push(hint.to_string());
+        self
+    }
+
+    /// Points at another place to do with the error, if it has a location
+    pub fn with_label<S: ToString>(mut self, location: Option<Location>, message: S) -> Self {
+        if let Some(location) = location {
+            self.details.labels.push(Label {
+                location,
+                message: message.to_string(),
+            });
+        }
+        self
+    }
+
+    /// Suggests replacing what is at `location` with `replacement`
+    pub fn with_fix<S: ToString, R: ToString>(
+        mut self,
+        location: Option<Location>,
+        message: S,
+        replacement: R,
+    ) -> Self {
+        if let Some(location) = location {
+            self.details.fixes.push(Fix {
+                message: message.to_string(),
+                location,
+                replacement: replacement.to_string(),
+            });
+        }
+        self
+    }
+
+    /// Sets the location unless an inner call already set a closer one
+    pub fn or_at(mut self, location: Location) -> Self {
+        self.location.get_or_insert(location);
+        self
+    }
+
+    /// Sets the location and the snippet showing it, unless an inner call
+    /// already set a closer one
+    pub fn or_in(mut self, span: Span, sources: &SourceMap) -> Self {
+        if self.location.is_none() {
+            self.location = sources.location(span);
+            self.details.snippet = sources.snippet(span);
+        }
+        self
+    }
+}
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{}: {}", location, self.message)?,
+            None => write!(f, "{}", self.message)?,
+        }
+        if let ErrorKind::Lint(lint) = self.kind {
+            write!(f, " [{}]", lint)?;
+        }
+        write!(f, "{}", self.details)
+    }
+}
+// Everything but the headline, each part on lines of its own
+impl Display for Details {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        if let Some(snippet) = &self.snippet {
+            write!(f, "\n{}", snippet)?;
+        }
+        for label in &self.labels {
+            write!(f, "\n  = note: {}: {}", label.location, label.message)?;
+        }
+        for hint in &self.hints {
+            write!(f, "\n  = hint: {}", hint)?;
+        }
+        let trace = &self.trace;
+        if !trace.is_empty() {
+            write!(f, "\nB call stack, innermost first:")?;
+        }
+        // Deep recursion is cut down to the calls at either end
+        let shown = if trace.len() > 2 * TRACE_ENDS {
+            TRACE_ENDS
+        } else {
+            trace.len()
+        };
+        for frame in &trace[..shown] {
+            write!(f, "\n  {}", frame)?;
+        }
+        if shown < trace.len() {
+            let hidden = trace.len() - 2 * shown;
+            write!(f, "\n  ... {} more calls ...", hidden)?;
+            for frame in &trace[trace.len() - shown..] {
+                write!(f, "\n  {}", frame)?;
+            }
+        }
+        for other in &self.others {
+            write!(f, "\n\n{}", other)?;
+        }
+        Ok(())
+    }
+}
+impl From<HeapError> for RuntimeError {
+    fn from(e: HeapError) -> Self {
+        let kind = match e {
+            HeapError::OutOfBounds { .. } => ErrorKind::IndexOutOfBounds,
+            _ => ErrorKind::Heap,
+        };
+        Self::new(kind, e)
+    }
+}
+
+/// A mistake the grammar finds in source that parses, like a vector size too
+/// big for a word, with the bytes it covers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub message: &'static str,
+    pub start: usize,
+    pub end: usize,
+}
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// How far into the source a parse error is
+pub fn error_offset<T>(e: &ParseError<usize, T, SyntaxError>) -> usize {
+    match e {
+        ParseError::InvalidToken { location } | ParseError::UnrecognizedEOF { location, .. } => {
+            *location
+        }
+        ParseError::UnrecognizedToken { token, .. } | ParseError::ExtraToken { token } => token.0,
+        ParseError::User { error } => error.start,
+    }
+}
+
+/// A parse error in one of `sources` described for a person rather than by
+/// byte offsets
+pub fn parse_error<T: Display>(
+    e: &ParseError<usize, T, SyntaxError>,
+    file: FileId,
+    sources: &SourceMap,
+) -> RuntimeError {
+    let source = &sources
+        .get(file)
+        .expect("file is not in the source map")
+        .source;
+    let at = |start, end| sources.location(Span::new(file, start, end));
+    let offset = error_offset(e);
+    let (message, end, expected) = match e {
+        ParseError::InvalidToken { location } => {
+            let found = source[*location..].chars().next().unwrap_or(' ');
+            let error = RuntimeError::new(
+                ErrorKind::Parse,
+                format!("Unexpected character `{}`", found),
+            );
+            let error = match found {
+                '"' | '\'' => error.with_hint(format!(
+                    "{} constants must be closed with `{}` on the same line, \
+                     write `*{}` for one inside",
+                    if found == '"' { "String" } else { "Character" },
+                    found,
+                    found
+                )),
+                _ => error,
+            };
+            let span = Span::new(file, offset, offset + found.len_utf8());
+            return error.or_in(span, sources);
+        }
+        ParseError::UnrecognizedEOF { expected, .. } => {
+            ("Unexpected end of file".to_string(), offset, &expected[..])
+        }
+        ParseError::UnrecognizedToken {
+            token: (_, token, end),
+            expected,
+        } => (format!("Unexpected `{}`", token), *end, &expected[..]),
+        ParseError::ExtraToken {
+            token: (_, token, end),
+        } => (
+            format!("Unexpected `{}` after the end of the program", token),
+            *end,
+            &[][..],
+        ),
+        ParseError::User { error } => (error.to_string(), error.end, &[][..]),
+    };
+
+    let expected = expected_tokens(expected);
+    let message = match expected.len() {
+        0 => message,
+        1 => format!("{}, expected {}", message, expected[0]),
+        n => format!(
+            "{}, expected one of {} or {}",
+            message,
+            expected[..n - 1].join(", "),
+            expected[n - 1]
+        ),
+    };
+    let mut error = RuntimeError::new(ErrorKind::Parse, message);
+
+    let before = source[..offset].trim_end();
+    // The statement so far, from whatever ended or opened a block before it
+    let statement = before[before.rfind(&[';', '{', '}'][..]).map_or(0, |i| i + 1)..].trim_start();
+    let declaring = ["auto", "extrn"].iter().any(|keyword| {
+        statement
+            .strip_prefix(keyword)
+            .is_some_and(|rest| rest.starts_with(char::is_whitespace))
+    });
+    let eof = matches!(e, ParseError::UnrecognizedEOF { .. });
+    if declaring && source[offset..].starts_with('=') {
+        error = error.with_hint(
+            "Variables cannot be given a value where they are declared, \
+             assign it in a statement after the declaration",
+        );
+    } else if expected.iter().any(|token| token == "`;`")
+        && source[before.len()..offset].contains('\n')
+    {
+        let line = before.matches('\n').count() + 1;
+        error = error
+            .with_hint(format!("Is a `;` missing at the end of line {}?", line))
+            .with_fix(at(before.len(), before.len()), "Add a `;`", ";");
+    }
+    if eof && expected.iter().any(|token| token == "`}`") {
+        error = error
+            .with_hint("Is a `}` missing at the end of a block?")
+            .with_fix(at(source.len(), source.len()), "Close the block", "}\n");
+    }
+    if expected == ["`{`"] && before.ends_with(')') {
+        error = error.with_hint(
+            "The body of an `if` must be in braces, even a single statement, \
+             like `if (x) { return; }`",
+        );
+    }
+    error.or_in(Span::new(file, offset, end), sources)
+}
+
+// The tokens a parse error lists as expected, named the way they are
+// written in B rather than by the grammar's regular expressions. Anything
+// that can start an expression is folded into "an expression", and anything
+// that can go on after one into "an operator"
+fn expected_tokens(expected: &[String]) -> Vec<String> {
+    const EXPRESSION: &[&str] = &[
+        "\"!\"", "\"#-\"", "\"(\"", "\"+\"", "\"++\"", "\"-\"", "\"--\"", "\"[\"", "\"~\"",
+    ];
+    const OPERATOR: &[&str] = &[
+        "\"!=\"", "\"#!=\"", "\"#*\"", "\"#+\"", "\"#-\"", "\"#/\"", "\"#<\"", "\"#<=\"",
+        "\"#==\"", "\"#>\"", "\"#>=\"", "\"%\"", "\"&\"", "\"*\"", "\"+\"", "\"++\"", "\"-\"",
+        "\"--\"", "\"/\"", "\"<\"", "\"<<\"", "\"<=\"", "\"=\"", "\"=%\"", "\"=&\"", "\"=*\"",
+        "\"=+\"", "\"=-\"", "\"=/\"", "\"=<<\"", "\"==\"", "\"=>>\"", "\"=^\"", "\"=|\"", "\">\"",
+        "\">=\"", "\">>\"", "\"?\"", "\"[\"", "\"^\"", "\"|\"",
+    ];
+    let expects_all = |tokens: &[&str]| {
+        tokens
+            .iter()
+            .all(|token| expected.iter().any(|e| e == token))
+    };
+    let starts_expression = expects_all(EXPRESSION);
+    // Where an expression could go on the binary operators are expected,
+    // which ones depends on what was just parsed
+    let continues_expression = expects_all(&["\"+\"", "\"*\"", "\"==\"", "\"?\""]);
+
+    let mut names = BTreeSet::new();
+    for token in expected {
+        if starts_expression && EXPRESSION.contains(&token.as_str()) {
+            continue;
+        }
+        if continues_expression && OPERATOR.contains(&token.as_str()) {
+            continue;
+        }
+        // Terminals matched by a regular expression are printed as r#"..."#
+        let name = if !token.starts_with("r#") {
+            format!("`{}`", token.trim_matches('"'))
+        } else if token.contains("a-zA-Z") {
+            "a name".to_string()
+        } else if token.contains("\\d") {
+            "a number".to_string()
+        } else if token.starts_with("r#\"'") {
+            "a character constant".to_string()
+        } else {
+            "a string".to_string()
+        };
+        names.insert(name);
+    }
+    if starts_expression {
+        for operand in &["a name", "a number", "a string", "a character constant"] {
+            names.remove(*operand);
+        }
+        names.insert("an expression".to_string());
+    }
+    if continues_expression {
+        names.insert("an operator".to_string());
+    }
+    names.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::parse;
+
+    fn parse_errors(source: &str) -> Vec<RuntimeError> {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.b", source);
+        parse(&sources, file).1
+    }
+
+    #[test]
+    fn operators_are_expected_as_one() {
+        let errors = parse_errors("main() {\n    return 1 2;\n}\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message,
+            "Unexpected `2`, expected one of `)`, `,`, `:`, `;`, `]` or an operator"
+        );
+    }
+
+    #[test]
+    fn errors_at_the_start_of_a_file_have_a_location() {
+        for source in ["", "+"] {
+            let errors = parse_errors(source);
+            assert_eq!(errors.len(), 1, "{:?}", source);
+            let location = errors[0].location.as_ref().unwrap();
+            assert_eq!((location.line, location.column), (1, 1));
+        }
+    }
+
+    #[test]
+    fn initialized_declarations_are_hinted_wherever_they_start() {
+        let hint = "Variables cannot be given a value where they are declared, \
+                    assign it in a statement after the declaration";
+        for source in [
+            "main() {\n    auto x = 1;\n}\n",
+            "main() { auto x = 1; }\n",
+            "main() {\n    auto x,\n        y = 1;\n}\n",
+            "main() { extrn x = 1; }\n",
+        ] {
+            let errors = parse_errors(source);
+            assert_eq!(errors.len(), 1, "{}", source);
+            assert_eq!(errors[0].details.hints, [hint], "{}", source);
+        }
+        // Only the statement the error is in counts, not the line
+        let errors = parse_errors("main() {\n    auto x; x = = 1;\n}\n");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].details.hints.iter().all(|h| h != hint));
+    }
+}
@@ -0,0 +1,72 @@
+//! Running B source handed over at runtime by the program itself.
+
+use super::{
+    error::{error_offset, parse_error},
+    strings::string_arg,
+    Function,
+};
+use crate::{
+    ast::{Const, Expression},
+    grammar::ExprParser,
+    Parser,
+};
+
+#[allow(non_snake_case)]
+impl Function {
+    /// `eval(src)` runs `src` as either an expression, evaluated where eval
+    /// was called and returned, or definitions, installed globally. Source
+    /// that does not parse gives back an error value rather than stopping
+    /// the program.
+    pub fn Eval() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let source: String = string_arg(interpreter, "eval", &args, 0)?
+                .into_iter()
+                .collect();
+            // Spans in the parsed code point into the source it was given
+            let file = interpreter.sources.add("<eval>", &source);
+
+            // Expressions have no statements to recover at
+            let expr_error = match ExprParser::new().parse(file, &mut vec![], &source) {
+                Ok(expr) => return interpreter.eval_expr(expr),
+                Err(e) => e,
+            };
+            let mut recovered = vec![];
+            let file_error = match Parser::new().parse(file, &mut recovered, &source) {
+                Ok(_) if !recovered.is_empty() => recovered.remove(0).error,
+                Ok(file) => {
+                    interpreter.eval(file)?;
+                    return Ok(Expression::constant(Const::Integer(0)));
+                }
+                Err(e) => e,
+            };
+            // Report whichever of the expression and file parses got further
+            let error = if error_offset(&file_error) >= error_offset(&expr_error) {
+                file_error
+            } else {
+                expr_error
+            };
+            let message = parse_error(&error, file, &interpreter.sources).message;
+            Ok(Expression::constant(Const::Error(message)))
+        });
+
+        func
+    }
+
+    /// `iserr(x)` is 1 if `x` is an error value, such as `eval` returns for
+    /// source that does not parse
+    pub fn Iserr() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|_, args| {
+            let error = matches!(
+                args.first().and_then(Expression::expect_const),
+                Some(Const::Error(_))
+            );
+            Ok(Expression::constant(Const::Integer(error as i64)))
+        });
+
+        func
+    }
+}
@@ -0,0 +1,283 @@
+//! The template language of the `format` builtin.
+//!
+//! `{}` takes the next argument and `{n}` the n-th one. A spec after a colon
+//! controls how it is written, `{:>8}`, `{:*<8}`, `{:^8}` align it in a
+//! field, `{:08}` pads a number with zeros and `{:x}`, `{:X}`, `{:o}`,
+//! `{:b}` and `{:d}` pick the radix. `{:f}` writes a word holding a floating
+//! point number, as made by the `#` operators. `{{` and `}}` are literal
+//! braces. Vectors and maps are written out in full, see `Const::render`.
+
+use std::{iter::Peekable, str::Chars};
+
+use super::{radix_text, Interpreter};
+use crate::ast::Const;
+use num_bigint::Sign;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Debug)]
+struct Spec {
+    fill: char,
+    align: Option<Align>,
+    zero: bool,
+    width: usize,
+    radix: Option<(u32, bool)>,
+    float: bool,
+}
+
+fn align(ch: char) -> Option<Align> {
+    match ch {
+        '<' => Some(Align::Left),
+        '>' => Some(Align::Right),
+        '^' => Some(Align::Center),
+        _ => None,
+    }
+}
+
+fn parse_spec(spec: &str) -> Result<Spec, String> {
+    let chars: Vec<char> = spec.chars().collect();
+    let mut result = Spec {
+        fill: ' ',
+        align: None,
+        zero: false,
+        width: 0,
+        radix: None,
+        float: false,
+    };
+
+    let mut i = 0;
+    if let Some(a) = chars.get(1).copied().and_then(align) {
+        result.fill = chars[0];
+        result.align = Some(a);
+        i = 2;
+    } else if let Some(a) = chars.first().copied().and_then(align) {
+        result.align = Some(a);
+        i = 1;
+    }
+
+    if chars.get(i) == Some(&'0') {
+        result.zero = true;
+        i += 1;
+    }
+
+    let digits: String = chars[i..]
+        .iter()
+        .take_while(|ch| ch.is_ascii_digit())
+        .collect();
+    if !digits.is_empty() {
+        result.width = digits
+            .parse()
+            .map_err(|_| format!("Invalid width in {{:{}}}", spec))?;
+        i += digits.len();
+    }
+
+    result.radix = match &chars[i..] {
+        [] => None,
+        ['d'] => Some((10, false)),
+        ['x'] => Some((16, false)),
+        ['X'] => Some((16, true)),
+        ['o'] => Some((8, false)),
+        ['b'] => Some((2, false)),
+        ['f'] => {
+            result.float = true;
+            None
+        }
+        _ => return Err(format!("Invalid format spec {{:{}}}", spec)),
+    };
+
+    Ok(result)
+}
+
+fn render(value: &Const, spec: &Spec, interpreter: &Interpreter) -> Result<String, String> {
+    let (sign, body) = match (value, spec.radix) {
+        (Const::Integer(_), _) if spec.float => {
+            let f = value.float();
+            let sign = if f.is_sign_negative() { "-" } else { "" };
+            (sign, f.abs().to_string())
+        }
+        (_, _) if spec.float => {
+            return Err(format!(
+                "Float format given for the non integer format argument {}",
+                interpreter.show(value)
+            ))
+        }
+        (Const::Integer(_), Some((radix, upper)))
+        | (Const::BigInteger(_), Some((radix, upper))) => {
+            let s = radix_text(value, radix, interpreter.word_size);
+            let s = if upper { s.to_uppercase() } else { s };
+            match s.strip_prefix('-') {
+                Some(magnitude) => ("-", magnitude.to_string()),
+                None => ("", s),
+            }
+        }
+        (Const::Integer(i), None) if *i < 0 => ("-", i.unsigned_abs().to_string()),
+        (Const::BigInteger(i), None) if i.sign() == Sign::Minus => ("-", i.magnitude().to_string()),
+        (_, Some(_)) => {
+            return Err(format!(
+                "Radix given for the non integer format argument {}",
+                interpreter.show(value)
+            ))
+        }
+        (value, None) => ("", interpreter.show(value)),
+    };
+
+    let len = sign.len() + body.chars().count();
+    if len >= spec.width {
+        return Ok(format!("{}{}", sign, body));
+    }
+    let padding = spec.width - len;
+
+    if spec.zero && spec.align.is_none() {
+        return Ok(format!("{}{}{}", sign, "0".repeat(padding), body));
+    }
+
+    let fill = |n: usize| spec.fill.to_string().repeat(n);
+    let text = format!("{}{}", sign, body);
+    let default = match value {
+        Const::Integer(_) | Const::BigInteger(_) => Align::Right,
+        _ => Align::Left,
+    };
+    Ok(match spec.align.unwrap_or(default) {
+        Align::Left => format!("{}{}", text, fill(padding)),
+        Align::Right => format!("{}{}", fill(padding), text),
+        Align::Center => format!(
+            "{}{}{}",
+            fill(padding / 2),
+            text,
+            fill(padding - padding / 2)
+        ),
+    })
+}
+
+fn placeholder(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut placeholder = String::new();
+    loop {
+        match chars.next() {
+            Some('}') => return Ok(placeholder),
+            Some(ch) => placeholder.push(ch),
+            None => return Err(format!("Unterminated placeholder {{{}", placeholder)),
+        }
+    }
+}
+
+/// Fills the placeholders of `template` with `args`, failing if it is
+/// malformed or refers to arguments that were not given
+pub fn format(template: &str, args: &[Const], interpreter: &Interpreter) -> Result<String, String> {
+    let mut result = String::new();
+    let mut next = 0;
+    let mut chars = template.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match (ch, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                result.push(ch);
+            }
+            ('{', _) => {
+                let placeholder = placeholder(&mut chars)?;
+                let (index, spec) = match placeholder.find(':') {
+                    Some(colon) => (&placeholder[..colon], &placeholder[colon + 1..]),
+                    None => (placeholder.as_str(), ""),
+                };
+
+                let index = if index.is_empty() {
+                    next += 1;
+                    next - 1
+                } else {
+                    index
+                        .parse()
+                        .map_err(|_| format!("Invalid placeholder {{{}}}", placeholder))?
+                };
+                // Placeholders count arguments from 0
+                let value = args.get(index).ok_or_else(|| {
+                    format!(
+                        "Placeholder {{{}}} refers to argument index {}, {} given",
+                        placeholder,
+                        index,
+                        match args.len() {
+                            1 => "1 argument was".to_string(),
+                            n => format!("{} arguments were", n),
+                        }
+                    )
+                })?;
+
+                result.push_str(&render(value, &parse_spec(spec)?, interpreter)?);
+            }
+            ('}', _) => return Err("Unmatched } in format string, use }} for a brace".into()),
+            (ch, _) => result.push(ch),
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(template: &str, args: &[Const]) -> Result<String, String> {
+        format(template, args, &Interpreter::new())
+    }
+
+    #[test]
+    fn placeholders_take_arguments_in_order_or_by_position() {
+        let args = [Const::Integer(1), Const::String("two".into())];
+        assert_eq!(fill("{} and {}", &args).unwrap(), "1 and two");
+        assert_eq!(fill("{1} {0} {1}", &args).unwrap(), "two 1 two");
+        assert_eq!(fill("{{{}}}", &args).unwrap(), "{1}");
+    }
+
+    #[test]
+    fn specs_align_pad_and_pick_a_radix() {
+        let format_one = |spec: &str, value: Const| fill(spec, &[value]).unwrap();
+        assert_eq!(format_one("{:5}", Const::Integer(42)), "   42");
+        assert_eq!(format_one("{:5}", Const::String("ab".into())), "ab   ");
+        assert_eq!(format_one("{:<5}|", Const::Integer(42)), "42   |");
+        assert_eq!(format_one("{:*^7}", Const::Integer(42)), "**42***");
+        assert_eq!(format_one("{:05}", Const::Integer(-42)), "-0042");
+        assert_eq!(format_one("{:x}", Const::Integer(255)), "ff");
+        assert_eq!(format_one("{:X}", Const::Integer(255)), "FF");
+        assert_eq!(format_one("{:o}", Const::Integer(8)), "10");
+        assert_eq!(format_one("{:08b}", Const::Integer(5)), "00000101");
+        assert_eq!(format_one("{:d}", Const::Integer(-3)), "-3");
+        assert_eq!(format_one("{:f}", Const::from_float(-1.5)), "-1.5");
+    }
+
+    #[test]
+    fn malformed_templates_are_errors() {
+        let args = [Const::Integer(1)];
+        assert!(fill("{", &args).is_err());
+        assert!(fill("}", &args).is_err());
+        assert!(fill("{} {}", &args).is_err());
+        assert!(fill("{a}", &args).is_err());
+        assert!(fill("{:q}", &args).is_err());
+        assert!(fill("{:x}", &[Const::String("s".into())]).is_err());
+    }
+
+    #[test]
+    fn errors_name_arguments_the_way_they_are_written() {
+        let args = [Const::Integer(1)];
+        assert_eq!(
+            fill("{} {}", &args).unwrap_err(),
+            "Placeholder {} refers to argument index 1, 1 argument was given"
+        );
+        assert_eq!(
+            fill("{0}", &[]).unwrap_err(),
+            "Placeholder {0} refers to argument index 0, 0 arguments were given"
+        );
+        let string = [Const::String("s".into())];
+        assert_eq!(
+            fill("{:x}", &string).unwrap_err(),
+            "Radix given for the non integer format argument s"
+        );
+        assert_eq!(
+            fill("{:f}", &string).unwrap_err(),
+            "Float format given for the non integer format argument s"
+        );
+    }
+}
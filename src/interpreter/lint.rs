@@ -0,0 +1,493 @@
+//! Warnings about code that is valid B but probably not what was meant, like
+//! `x =- 1` or a `case` that runs on into the next one. Each lint has a name
+//! and a level, so any of them can be turned off or made an error.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use super::{
+    check::{expression, statements, visit_expression, visit_expressions},
+    Details, ErrorKind, Fix, Location, RuntimeError,
+};
+use crate::{
+    ast::{CaseStatement, Expression, Statement, VariableScope},
+    source::{SourceMap, Span},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// `x =- 1`, which subtracts rather than assigning -1
+    AssignMinus,
+    /// An assignment as the whole condition of an `if` or `while`
+    AssignInCondition,
+    /// An `auto` that is never used
+    UnusedAuto,
+    /// Statements after a `return` in the same block
+    UnreachableCode,
+    /// A `case` that runs on into the next one
+    SwitchFallthrough,
+}
+impl Lint {
+    pub const ALL: [Lint; 5] = [
+        Lint::AssignMinus,
+        Lint::AssignInCondition,
+        Lint::UnusedAuto,
+        Lint::UnreachableCode,
+        Lint::SwitchFallthrough,
+    ];
+
+    /// What it is called on the command line and in warnings
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::AssignMinus => "assign-minus",
+            Lint::AssignInCondition => "assign-in-condition",
+            Lint::UnusedAuto => "unused-auto",
+            Lint::UnreachableCode => "unreachable-code",
+            Lint::SwitchFallthrough => "switch-fallthrough",
+        }
+    }
+}
+impl FromStr for Lint {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Lint::ALL
+            .iter()
+            .copied()
+            .find(|lint| lint.name() == name)
+            .ok_or_else(|| format!("There is no lint called {}", name))
+    }
+}
+impl Display for Lint {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// What happens when a lint finds something
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// Nothing
+    Allow,
+    /// A warning is shown and the program still runs
+    Warn,
+    /// It is an error and the program does not run
+    Deny,
+}
+
+/// Something a lint found
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub lint: Lint,
+    pub level: Level,
+    pub message: String,
+    pub location: Option<Location>,
+    pub details: Box<Details>,
+}
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        if let Some(location) = &self.location {
+            write!(f, "{}: ", location)?;
+        }
+        write!(
+            f,
+            "warning: {} [{}]{}",
+            self.message, self.lint, self.details
+        )
+    }
+}
+impl From<Warning> for RuntimeError {
+    fn from(warning: Warning) -> Self {
+        Self {
+            kind: ErrorKind::Lint(warning.lint),
+            message: warning.message,
+            location: warning.location,
+            details: warning.details,
+        }
+    }
+}
+
+/// Runs the lints over a parsed program, every one at `Level::Warn` unless
+/// set otherwise
+pub struct Linter<'a> {
+    sources: &'a SourceMap,
+    levels: HashMap<Lint, Level>,
+    warnings: Vec<Warning>,
+}
+
+impl<'a> Linter<'a> {
+    pub fn new(sources: &'a SourceMap) -> Self {
+        Self {
+            sources,
+            levels: HashMap::new(),
+            warnings: vec![],
+        }
+    }
+
+    pub fn with_level(mut self, lint: Lint, level: Level) -> Self {
+        self.levels.insert(lint, level);
+        self
+    }
+
+    pub fn with_levels(mut self, levels: &HashMap<Lint, Level>) -> Self {
+        self.levels.extend(levels);
+        self
+    }
+
+    /// Everything the lints not allowed find in `file`, in the order it is
+    /// in the source
+    pub fn lint(mut self, file: &[Statement]) -> Vec<Warning> {
+        for stmt in file {
+            if let Statement::FunctionDefinition { body, .. } = stmt {
+                self.unused_autos(body);
+                self.statement(body);
+            }
+        }
+        self.warnings.sort_by_key(|warning| {
+            let location = warning.location.as_ref();
+            location.map(|location| (location.line, location.column))
+        });
+        self.warnings
+    }
+
+    // Each fix replaces what is in its span with the string
+    fn warn(
+        &mut self,
+        lint: Lint,
+        message: String,
+        span: Span,
+        hint: &str,
+        fixes: Vec<(&str, Span, String)>,
+    ) {
+        let level = self.levels.get(&lint).copied().unwrap_or(Level::Warn);
+        if level == Level::Allow {
+            return;
+        }
+        let fixes = fixes
+            .into_iter()
+            .filter_map(|(message, span, replacement)| {
+                Some(Fix {
+                    message: message.to_string(),
+                    location: self.sources.location(span)?,
+                    replacement,
+                })
+            })
+            .collect();
+        self.warnings.push(Warning {
+            lint,
+            level,
+            message,
+            location: self.sources.location(span),
+            details: Box::new(Details {
+                snippet: self.sources.snippet(span),
+                hints: vec![hint.to_string()],
+                fixes,
+                ..Details::default()
+            }),
+        });
+    }
+
+    // The source a span covers
+    fn text(&self, span: Span) -> &'a str {
+        self.sources.text(span).unwrap_or_default()
+    }
+
+    // Where `operator` is between the two sides of an assignment, with the
+    // space around it. None if there is more between them, like a bracket
+    fn operator(&self, lhs: &Expression, rhs: &Expression, operator: &str) -> Option<Span> {
+        let (lhs, rhs) = (lhs.span()?, rhs.span()?);
+        let between = Span::new(lhs.file, lhs.end, rhs.start);
+        (self.text(between).trim() == operator).then_some(between)
+    }
+
+    fn unused_autos(&mut self, body: &Statement) {
+        let mut used = HashSet::new();
+        visit_expressions(body, &mut |expr| {
+            if let Expression::Identifier { ident, .. } = expr {
+                used.insert(ident.to_string());
+            }
+        });
+
+        let mut declarations = vec![];
+        let mut stack = vec![body];
+        while let Some(stmt) = stack.pop() {
+            if let Statement::Declaration {
+                scope: VariableScope::Local,
+                idents,
+                span,
+            } = stmt
+            {
+                declarations.extend(idents.iter().map(|ident| (ident.to_string(), *span)));
+            }
+            stack.extend(statements(stmt).into_iter().rev());
+        }
+
+        for (name, span) in declarations {
+            if !used.contains(&name) {
+                self.warn(
+                    Lint::UnusedAuto,
+                    format!("{} is declared but never used", name),
+                    span,
+                    "Remove it from the declaration if it is not needed",
+                    vec![],
+                );
+            }
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Compound(stmts, _) => self.unreachable(stmts),
+            Statement::Conditional { condition, .. } | Statement::Loop { condition, .. } => {
+                self.condition(condition)
+            }
+            Statement::Switch { cases, .. } => self.fallthrough(cases),
+            _ => {}
+        }
+        if let Some(expr) = expression(stmt) {
+            self.assign_minus(expr);
+        }
+        for stmt in statements(stmt) {
+            self.statement(stmt);
+        }
+    }
+
+    fn assign_minus(&mut self, expr: &Expression) {
+        let mut found = vec![];
+        visit_expression(expr, &mut |expr| {
+            if let Expression::AssignSubtract { lhs, rhs, span } = expr {
+                found.push((&**lhs, &**rhs, *span));
+            }
+        });
+        for (lhs, rhs, span) in found {
+            let mut fixes = vec![];
+            if let Some(operator) = self.operator(lhs, rhs, "=-") {
+                let (lhs, rhs) = (
+                    self.text(lhs.span().unwrap()),
+                    self.text(rhs.span().unwrap()),
+                );
+                fixes.push(("Assign a negative number", operator, " = -".to_string()));
+                let subtraction = format!("{} = {} - {}", lhs, lhs, rhs);
+                fixes.push(("Write the subtraction out", span, subtraction));
+            }
+            self.warn(
+                Lint::AssignMinus,
+                "`=-` subtracts, it does not assign a negative number".into(),
+                span,
+                "Write `x = -1` to assign a negative number, or `x = x - 1` \
+                 to make the subtraction clear",
+                fixes,
+            );
+        }
+    }
+
+    fn condition(&mut self, condition: &Expression) {
+        let assigns = matches!(
+            condition,
+            Expression::Assign { .. }
+                | Expression::AssignOr { .. }
+                | Expression::AssignXor { .. }
+                | Expression::AssignAnd { .. }
+                | Expression::AssignShiftLeft { .. }
+                | Expression::AssignShiftRight { .. }
+                | Expression::AssignAdd { .. }
+                | Expression::AssignSubtract { .. }
+                | Expression::AssignMultiply { .. }
+                | Expression::AssignDivide { .. }
+                | Expression::AssignModulo { .. }
+        );
+        if let (true, Some(span)) = (assigns, condition.span()) {
+            let mut fixes = vec![];
+            if let Expression::Assign { lhs, rhs, .. } = condition {
+                if let Some(operator) = self.operator(lhs, rhs, "=") {
+                    fixes.push(("Compare instead", operator, " == ".to_string()));
+                }
+            }
+            self.warn(
+                Lint::AssignInCondition,
+                "The condition is an assignment".into(),
+                span,
+                "Use `==` to compare, or assign before the condition",
+                fixes,
+            );
+        }
+    }
+
+    // Statements after a return in the same block never run
+    fn unreachable(&mut self, stmts: &[Statement]) {
+        let returns = stmts
+            .iter()
+            .position(|stmt| matches!(stmt, Statement::Return(..)));
+        let after = match returns {
+            Some(i) => &stmts[i + 1..],
+            None => return,
+        };
+        if let (Some(first), Some(last)) = (after.first(), after.last()) {
+            let span = first.span().to(last.span());
+            self.warn(
+                Lint::UnreachableCode,
+                "This code can never run, it comes after a `return`".into(),
+                span,
+                "Remove it, or move the `return` after it",
+                vec![("Remove it", span, String::new())],
+            );
+        }
+    }
+
+    fn fallthrough(&mut self, cases: &[CaseStatement]) {
+        for pair in cases.windows(2) {
+            let (case, next) = (&pair[0], &pair[1]);
+            // Cases with no statements of their own share the next one's
+            let last = match case.body.as_ref().and_then(|body| body.last()) {
+                Some(last) => last,
+                None => continue,
+            };
+            let ends = matches!(last, Statement::Break(_) | Statement::Return(..));
+            if !ends {
+                let end = last.span();
+                let end = Span::new(end.file, end.end, end.end);
+                self.warn(
+                    Lint::SwitchFallthrough,
+                    format!("case {} falls through into case {}", case.case, next.case),
+                    case.span,
+                    "End it with `break;` if it should not run the next case too",
+                    vec![("Add a `break;`", end, " break;".to_string())],
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{parse, Interpreter};
+
+    fn lint_with(source: &str, levels: &[(Lint, Level)]) -> Vec<Warning> {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.b", source);
+        let (program, errors) = parse(&sources, file);
+        assert!(errors.is_empty(), "{:?}", errors);
+        levels
+            .iter()
+            .fold(Linter::new(&sources), |linter, &(lint, level)| {
+                linter.with_level(lint, level)
+            })
+            .lint(&program)
+    }
+
+    // The one warning `body` gives inside a function
+    fn only_warning(body: &str) -> Warning {
+        let warnings = lint_with(&format!("f(a) {{ {} }}", body), &[]);
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        warnings.into_iter().next().unwrap()
+    }
+
+    fn fixes(warning: &Warning) -> Vec<&str> {
+        warning
+            .details
+            .fixes
+            .iter()
+            .map(|fix| fix.replacement.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn assign_minus() {
+        let warning = only_warning("a =- 1;");
+        assert_eq!(warning.lint, Lint::AssignMinus);
+        assert_eq!(fixes(&warning), [" = -", "a = a - 1"]);
+        let warnings = lint_with("f(a) { a = -1; a =-a; }", &[]);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].lint, Lint::AssignMinus);
+        assert_eq!(warnings[0].location.as_ref().unwrap().column, 16);
+    }
+
+    #[test]
+    fn assign_in_condition() {
+        let warning = only_warning("if (a = 1) { return a; }");
+        assert_eq!(warning.lint, Lint::AssignInCondition);
+        assert_eq!(fixes(&warning), [" == "]);
+        assert_eq!(
+            only_warning("while (a =+ 1) {}").lint,
+            Lint::AssignInCondition
+        );
+        assert!(lint_with("f(a) { if (a == 1) { return a; } }", &[]).is_empty());
+    }
+
+    #[test]
+    fn unused_auto() {
+        let warning = only_warning("auto b, c; c = a; return c;");
+        assert_eq!(warning.lint, Lint::UnusedAuto);
+        assert_eq!(warning.message, "b is declared but never used");
+        assert!(lint_with("f() { extrn b; auto c; c = 1; return c; }", &[]).is_empty());
+    }
+
+    #[test]
+    fn unreachable_code() {
+        let warning = only_warning("return a; a = 1; a = 2;");
+        assert_eq!(warning.lint, Lint::UnreachableCode);
+        assert_eq!(fixes(&warning), [""]);
+        let location = warning.details.fixes[0].location.clone();
+        assert_eq!((location.column, location.end_column), (18, 31));
+    }
+
+    #[test]
+    fn switch_fallthrough() {
+        let warning = only_warning("switch (a) { case 1: a = 2; case 2: case 3: return a; }");
+        assert_eq!(warning.lint, Lint::SwitchFallthrough);
+        assert_eq!(warning.message, "case 1 falls through into case 2");
+        assert_eq!(fixes(&warning), [" break;"]);
+    }
+
+    #[test]
+    fn levels_turn_lints_off_or_into_errors() {
+        let source = "f(a) { a =- 1; return a; a = 2; }";
+        let warnings = lint_with(source, &[]);
+        let found: Vec<_> = warnings
+            .iter()
+            .map(|warning| (warning.lint, warning.level))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Lint::AssignMinus, Level::Warn),
+                (Lint::UnreachableCode, Level::Warn)
+            ]
+        );
+
+        let levels = [
+            (Lint::AssignMinus, Level::Allow),
+            (Lint::UnreachableCode, Level::Deny),
+        ];
+        let warnings = lint_with(source, &levels);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            (warnings[0].lint, warnings[0].level),
+            (Lint::UnreachableCode, Level::Deny)
+        );
+    }
+
+    #[test]
+    fn denied_lints_stop_the_program() {
+        let source = "main() { auto x; x = 1; return x; x = 2; }";
+        let unused = Interpreter::new().with_lint_level(Lint::UnusedAuto, Level::Deny);
+        assert_eq!(unused.interpret_string(source).unwrap(), 1);
+        let e = Interpreter::new()
+            .with_lint_level(Lint::UnreachableCode, Level::Deny)
+            .interpret_string(source)
+            .unwrap_err();
+        assert_eq!(e.kind, ErrorKind::Lint(Lint::UnreachableCode));
+    }
+
+    #[test]
+    fn lints_are_found_by_name() {
+        for lint in Lint::ALL {
+            assert_eq!(lint.name().parse::<Lint>(), Ok(lint));
+        }
+        assert!("unused".parse::<Lint>().is_err());
+    }
+}
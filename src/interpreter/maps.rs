@@ -0,0 +1,139 @@
+//! Map builtins. A map made by `mapnew` is shared rather than copied, so
+//! passing one to a function lets it add to the caller's map. Keys are
+//! integers or strings, anything else is rejected.
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use super::{argument_error, Function, RuntimeError};
+use crate::ast::{Const, Expression, Map, MapKey};
+
+fn map_arg(name: &str, args: &[Expression]) -> Result<Map, RuntimeError> {
+    match args.first().and_then(Expression::expect_const) {
+        Some(Const::Map(map)) => Ok(map),
+        _ => Err(argument_error(format!(
+            "First argument of {} must be a map from mapnew",
+            name
+        ))),
+    }
+}
+
+fn key_arg(name: &str, args: &[Expression]) -> Result<MapKey, RuntimeError> {
+    args.get(1)
+        .and_then(Expression::expect_const)
+        .and_then(|key| MapKey::from_const(&key))
+        .ok_or_else(|| {
+            argument_error(format!(
+                "Second argument of {} must be an integer or a string",
+                name
+            ))
+        })
+}
+
+fn boolean(b: bool) -> Result<Expression, RuntimeError> {
+    Ok(Expression::constant(Const::Integer(b as i64)))
+}
+
+#[allow(non_snake_case)]
+impl Function {
+    pub fn Mapnew() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|_, _| {
+            Ok(Expression::constant(Const::Map(Rc::new(RefCell::new(
+                HashMap::new(),
+            )))))
+        });
+
+        func
+    }
+
+    /// `mapget(m, key)` is the value stored under `key`, or 0 if there is none
+    pub fn Mapget() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|_, args| {
+            let map = map_arg("mapget", &args)?;
+            let key = key_arg("mapget", &args)?;
+            let value = map.borrow().get(&key).cloned();
+            Ok(Expression::constant(value.unwrap_or(Const::Integer(0))))
+        });
+
+        func
+    }
+
+    /// `mapset(m, key, value)` stores `value` under `key` and returns it
+    pub fn Mapset() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|_, args| {
+            let map = map_arg("mapset", &args)?;
+            let key = key_arg("mapset", &args)?;
+            let value = args
+                .get(2)
+                .and_then(Expression::expect_const)
+                .ok_or_else(|| argument_error("mapset needs a value to store"))?;
+            map.borrow_mut().insert(key, value.clone());
+            Ok(Expression::constant(value))
+        });
+
+        func
+    }
+
+    pub fn Maphas() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|_, args| {
+            let map = map_arg("maphas", &args)?;
+            let key = key_arg("maphas", &args)?;
+            let has = map.borrow().contains_key(&key);
+            boolean(has)
+        });
+
+        func
+    }
+
+    /// `mapdel(m, key)` removes `key`, returning 1 if it was there
+    pub fn Mapdel() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|_, args| {
+            let map = map_arg("mapdel", &args)?;
+            let key = key_arg("mapdel", &args)?;
+            let removed = map.borrow_mut().remove(&key).is_some();
+            boolean(removed)
+        });
+
+        func
+    }
+
+    /// `mapkeys(m)` is a vector of the keys in order, integers before strings,
+    /// it has `mapsize(m)` elements
+    pub fn Mapkeys() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|_, args| {
+            let map = map_arg("mapkeys", &args)?;
+            let mut keys: Vec<Const> = map.borrow().keys().map(MapKey::to_const).collect();
+            keys.sort_by(|lhs, rhs| lhs.partial_cmp(rhs).unwrap());
+            Ok(Expression::constant(Const::Vector(
+                keys.into_iter()
+                    .map(|key| Box::new(Expression::constant(key)))
+                    .collect(),
+            )))
+        });
+
+        func
+    }
+
+    pub fn Mapsize() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|_, args| {
+            let map = map_arg("mapsize", &args)?;
+            let size = map.borrow().len();
+            Ok(Expression::constant(Const::Integer(size as i64)))
+        });
+
+        func
+    }
+}
@@ -0,0 +1,83 @@
+//! Builtins that look functions up by name at runtime, so a B program can
+//! find and call functions it does not name in its source.
+
+use super::{argument_error, strings::string_arg, ErrorKind, Function, RuntimeError};
+use crate::ast::{Const, Expression, Identifier};
+
+#[allow(non_snake_case)]
+impl Function {
+    /// `call(name, args)` calls the function called `name` with the elements
+    /// of the vector `args` and returns what it returns
+    pub fn Call() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let name = string_arg(interpreter, "call", &args, 0)?;
+            let ident = Identifier::Name(name.iter().collect());
+            if interpreter.get_func(&ident).is_none() {
+                return Err(RuntimeError::new(
+                    ErrorKind::UndefinedFunction,
+                    format!("call: {} is not a function", ident),
+                ));
+            }
+
+            let arguments = match args.get(1).and_then(Expression::expect_const) {
+                Some(Const::Vector(items)) => items,
+                Some(Const::HeapVector(vector)) => interpreter
+                    .heap
+                    .words(vector)?
+                    .iter()
+                    .map(|word| Box::new(Expression::constant(word.clone())))
+                    .collect(),
+                None => vec![],
+                _ => return Err(argument_error("Second argument of call must be a vector")),
+            };
+            interpreter.call_function(&ident, arguments)
+        });
+
+        func
+    }
+
+    /// `hasfunc(name)` is 1 if a function called `name` exists, builtin or
+    /// defined by the program
+    pub fn Hasfunc() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let name = string_arg(interpreter, "hasfunc", &args, 0)?;
+            let ident = Identifier::Name(name.iter().collect());
+            let exists = interpreter.get_func(&ident).is_some();
+            Ok(Expression::constant(Const::Integer(exists as i64)))
+        });
+
+        func
+    }
+
+    /// `funcs()` is a vector of the names of the functions the program
+    /// defines in alphabetical order, ending with a 0 the way a string ends
+    /// with `*e`, so a program can loop over it without knowing its length
+    pub fn Funcs() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, _| {
+            let mut names: Vec<String> = interpreter
+                .global_scope()
+                .functions
+                .iter()
+                .filter(|(_, function)| function.builtin.is_none())
+                .map(|(ident, _)| ident.to_string())
+                .collect();
+            names.sort();
+            Ok(Expression::constant(Const::Vector(
+                names
+                    .into_iter()
+                    .map(Const::String)
+                    .chain(std::iter::once(Const::Integer(0)))
+                    .map(|name| Box::new(Expression::constant(name)))
+                    .collect(),
+            )))
+        });
+
+        func
+    }
+}
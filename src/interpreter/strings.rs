@@ -0,0 +1,278 @@
+//! String builtins. Every string argument may be either a string constant or
+//! a vector of characters, one per word, and ends at its first `*e`.
+
+use std::cmp::Ordering;
+
+use super::{argument_error, integer_arg, ErrorKind, Function, Interpreter, RuntimeError};
+use crate::ast::{terminated, Const, Expression, END_OF_STRING};
+
+pub(super) fn string_arg(
+    interpreter: &Interpreter,
+    name: &str,
+    args: &[Expression],
+    position: usize,
+) -> Result<Vec<char>, RuntimeError> {
+    let words: Vec<Const> = match args.get(position).and_then(Expression::expect_const) {
+        Some(Const::String(s)) => return Ok(terminated(&s).chars().collect()),
+        Some(Const::HeapVector(vector)) => interpreter.heap.words(vector)?.clone(),
+        // Literal vectors are evaluated by now, anything else in one is not a
+        // character
+        Some(Const::Vector(words)) => words
+            .iter()
+            .map(|word| word.expect_const().unwrap_or(Const::Vector(vec![])))
+            .collect(),
+        _ => {
+            return Err(argument_error(format!(
+                "Argument {} of {} must be a string or a vector of characters",
+                position + 1,
+                name
+            )))
+        }
+    };
+    let mut chars = vec![];
+    for word in words {
+        let ch = match word {
+            Const::Integer(ch) => std::char::from_u32(ch as u32)
+                .ok_or_else(|| argument_error(format!("{}: {} is not a character", name, ch)))?,
+            _ => {
+                return Err(argument_error(format!(
+                    "{}: vector contains a non character value",
+                    name
+                )))
+            }
+        };
+        if ch == END_OF_STRING {
+            break;
+        }
+        chars.push(ch);
+    }
+    Ok(chars)
+}
+
+fn string(chars: &[char]) -> Result<Expression, RuntimeError> {
+    Ok(Expression::constant(Const::String(chars.iter().collect())))
+}
+
+fn integer(i: i64) -> Result<Expression, RuntimeError> {
+    Ok(Expression::constant(Const::Integer(i)))
+}
+
+fn position(haystack: &[char], needle: &[char]) -> i64 {
+    if needle.is_empty() {
+        return 0;
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|i| i as i64)
+        .unwrap_or(-1)
+}
+
+#[allow(non_snake_case)]
+impl Function {
+    /// `char(s, i)`, the i-th character of `s`, `*e` past its end
+    pub fn Char() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let s = string_arg(interpreter, "char", &args, 0)?;
+            let ch = match integer_arg("char", &args, 1)? {
+                i if i < 0 => return Err(argument_error(format!("char: negative index {}", i))),
+                i => s.get(i as usize).copied().unwrap_or(END_OF_STRING),
+            };
+            integer(ch as i64)
+        });
+
+        func
+    }
+
+    pub fn Strlen() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            integer(string_arg(interpreter, "strlen", &args, 0)?.len() as i64)
+        });
+
+        func
+    }
+
+    /// `strcmp(a, b)`, negative if `a` sorts before `b`, zero if they are
+    /// equal and positive otherwise
+    pub fn Strcmp() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let lhs = string_arg(interpreter, "strcmp", &args, 0)?;
+            let rhs = string_arg(interpreter, "strcmp", &args, 1)?;
+            integer(match lhs.cmp(&rhs) {
+                Ordering::Less => -1,
+                Ordering::Equal => 0,
+                Ordering::Greater => 1,
+            })
+        });
+
+        func
+    }
+
+    /// `strcpy(v, s)`, copies `s` and its `*e` into the vector `v`, which is
+    /// returned
+    pub fn Strcpy() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let vector = match args.first().and_then(Expression::expect_const) {
+                Some(Const::HeapVector(vector)) => vector,
+                _ => {
+                    return Err(argument_error(
+                        "First argument of strcpy must be a vector from getvec",
+                    ))
+                }
+            };
+            let s = string_arg(interpreter, "strcpy", &args, 1)?;
+
+            for (i, ch) in s.iter().chain(Some(&END_OF_STRING)).enumerate() {
+                interpreter
+                    .heap
+                    .set(vector, i as i64, Const::Integer(*ch as i64))?;
+            }
+            Ok(Expression::constant(Const::HeapVector(vector)))
+        });
+
+        func
+    }
+
+    pub fn Strcat() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let mut s = string_arg(interpreter, "strcat", &args, 0)?;
+            s.extend(string_arg(interpreter, "strcat", &args, 1)?);
+            string(&s)
+        });
+
+        func
+    }
+
+    /// `substr(s, start, len)`, `len` characters of `s` from `start`, or the
+    /// rest of it if there are fewer or no `len` is given
+    pub fn Substr() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let s = string_arg(interpreter, "substr", &args, 0)?;
+            let start = integer_arg("substr", &args, 1)?;
+            if start < 0 || start as usize > s.len() {
+                return Err(RuntimeError::new(
+                    ErrorKind::IndexOutOfBounds,
+                    format!(
+                        "substr: start {} out of bounds for string of length {}",
+                        start,
+                        s.len()
+                    ),
+                ));
+            }
+            let s = &s[start as usize..];
+            let len = match args.get(2) {
+                Some(_) => integer_arg("substr", &args, 2)?.max(0) as usize,
+                None => s.len(),
+            };
+            string(&s[..len.min(s.len())])
+        });
+
+        func
+    }
+
+    /// `strchr(s, c)`, the index of the first `c` in `s` or -1
+    pub fn Strchr() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let s = string_arg(interpreter, "strchr", &args, 0)?;
+            let ch = std::char::from_u32(integer_arg("strchr", &args, 1)? as u32);
+            integer(
+                s.iter()
+                    .position(|c| Some(*c) == ch)
+                    .map(|i| i as i64)
+                    .unwrap_or(-1),
+            )
+        });
+
+        func
+    }
+
+    /// `strstr(s, t)`, the index of the first occurrence of `t` in `s` or -1
+    pub fn Strstr() -> Self {
+        let mut func = Self::Builtin();
+
+        func.builtin = Some(|interpreter, args| {
+            let haystack = string_arg(interpreter, "strstr", &args, 0)?;
+            let needle = string_arg(interpreter, "strstr", &args, 1)?;
+            integer(position(&haystack, &needle))
+        });
+
+        func
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::Interpreter;
+
+    fn run(body: &str) -> i64 {
+        Interpreter::new()
+            .interpret_string(format!("main() {{ auto v; {} }}", body))
+            .unwrap()
+    }
+
+    #[test]
+    fn strings_end_at_their_terminator() {
+        assert_eq!(run("return strlen(\"hello\");"), 5);
+        assert_eq!(run("return strlen(\"he*ello\");"), 2);
+        assert_eq!(run("return char(\"abc\", 1);"), 'b' as i64);
+        assert_eq!(run("return char(\"abc\", 3);"), 4);
+    }
+
+    #[test]
+    fn vectors_of_characters_are_strings() {
+        assert_eq!(run("return strlen(['a', 'b', 'c']);"), 3);
+        assert_eq!(run("return strcmp(['a', 'b', '*e', 'c'], \"ab\");"), 0);
+        assert_eq!(
+            run("v = getvec(2); v[0] = 'h'; v[1] = 'i'; v[2] = '*e'; return strcmp(v, \"hi\");"),
+            0
+        );
+    }
+
+    #[test]
+    fn comparing() {
+        assert!(run("return strcmp(\"abc\", \"abd\");") < 0);
+        assert!(run("return strcmp(\"b\", \"abc\");") > 0);
+        assert!(run("return strcmp(\"ab\", \"abc\");") < 0);
+        assert_eq!(run("return strcmp(\"same\", \"same\");"), 0);
+    }
+
+    #[test]
+    fn copying_and_joining() {
+        assert_eq!(
+            run("v = getvec(5); strcpy(v, \"abc\"); return strcmp(v, \"abc\");"),
+            0
+        );
+        assert_eq!(run("return strcmp(strcat(\"ab\", ['c']), \"abc\");"), 0);
+        assert_eq!(run("return strcmp(substr(\"hello\", 1, 3), \"ell\");"), 0);
+        assert_eq!(run("return strcmp(substr(\"hello\", 3), \"lo\");"), 0);
+    }
+
+    #[test]
+    fn searching() {
+        assert_eq!(run("return strchr(\"hello\", 'l');"), 2);
+        assert_eq!(run("return strchr(\"hello\", 'z');"), -1);
+        assert_eq!(run("return strstr(\"hello\", \"llo\");"), 2);
+        assert_eq!(run("return strstr(\"hello\", \"\");"), 0);
+        assert_eq!(run("return strstr(\"hello\", \"world\");"), -1);
+    }
+
+    #[test]
+    fn other_values_are_not_strings() {
+        let result = Interpreter::new().interpret_string("main() { return strlen(5); }");
+        assert!(result.is_err());
+    }
+}
@@ -1,27 +1,161 @@
-use interpreter::Interpreter;
+use environment::PinnedEnvironment;
+use filesystem::DirectoryFileSystem;
+use interpreter::{ErrorFormat, Interpreter, Level, Lint, Uninitialized};
 use lalrpop_util::lalrpop_mod;
+use std::env;
 use std::io::{stdin, stdout};
 use std::io::{Read, Write};
+use std::process::exit;
 
-lalrpop_mod!(pub grammar);
+lalrpop_mod!(
+    // Generated, so its style is up to lalrpop
+    #[allow(clippy::all)]
+    pub grammar
+);
 
 pub mod ast;
+pub mod environment;
 pub mod expression_constructors;
+pub mod filesystem;
+pub mod heap;
 pub mod interpreter;
+pub mod source;
 
 pub type Parser = grammar::FileParser;
 fn main() {
-    let mut buffer = String::new();
-    stdout()
-        .write(b"Enter the name of the test you'd like to run (holly, fib): ")
-        .unwrap();
-    stdout().flush().unwrap();
-    stdin().read_line(&mut buffer);
-    println!();
+    // brust [--big-integers] [--word-size=bits] [--root=dir]
+    // [--allow=lint|--warn=lint|--deny=lint...]
+    // [--error-format=human|json] [--uninitialized=error|zero|poison]
+    // [--pin-environment=seconds[,seed]] [program [args...]], without a
+    // program one of the examples is run. Words are 64 bits unless given a
+    // size. The program's files are the ones under the root, the current
+    // directory unless given. Pinning the environment stops `time` and
+    // `clock` at the given time and 0 and seeds `rand` with the seed, 1
+    // unless given, for runs that give the same output every time
+    let mut args = env::args().skip(1).peekable();
+    let mut big_integers = false;
+    let mut word_size = 64;
+    let mut root = ".".to_string();
+    let mut lints = vec![];
+    let mut error_format = ErrorFormat::Human;
+    let mut uninitialized = Uninitialized::Error;
+    let mut pinned = None;
+    while let Some(arg) = args.next_if(|arg| arg.starts_with("--")) {
+        if arg == "--big-integers" {
+            big_integers = true;
+            continue;
+        }
+        let (flag, name) = arg.split_at(arg.find('=').unwrap_or(arg.len()));
+        let name = name.strip_prefix('=').unwrap_or_else(|| {
+            eprintln!("Unknown option {}", flag);
+            exit(1);
+        });
+        if flag == "--error-format" {
+            error_format = name.parse().unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                exit(1);
+            });
+            continue;
+        }
+        if flag == "--word-size" {
+            word_size = name.parse().unwrap_or_else(|_| {
+                eprintln!("--word-size needs a number of bits");
+                exit(1);
+            });
+            continue;
+        }
+        if flag == "--root" {
+            root = name.to_string();
+            continue;
+        }
+        if flag == "--pin-environment" {
+            let (time, seed) = name.split_once(',').unwrap_or((name, "1"));
+            match (time.parse::<i64>(), seed.parse::<i64>()) {
+                (Ok(time), Ok(seed)) => pinned = Some((time, seed)),
+                _ => {
+                    eprintln!(
+                        "--pin-environment needs a time in seconds since 1970, \
+                         optionally followed by a comma and a seed"
+                    );
+                    exit(1);
+                }
+            }
+            continue;
+        }
+        if flag == "--uninitialized" {
+            uninitialized = name.parse().unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                exit(1);
+            });
+            continue;
+        }
+        let level = match flag {
+            "--allow" => Level::Allow,
+            "--warn" => Level::Warn,
+            "--deny" => Level::Deny,
+            _ => {
+                eprintln!("Unknown option {}", flag);
+                exit(1);
+            }
+        };
+        match name.parse::<Lint>() {
+            Ok(lint) => lints.push((lint, level)),
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+    }
+    let path = args.next().unwrap_or_else(|| {
+        let mut buffer = String::new();
+        stdout()
+            .write(b"Enter the name of the test you'd like to run (holly, fib): ")
+            .unwrap();
+        stdout().flush().unwrap();
+        stdin().read_line(&mut buffer);
+        println!();
+        format!("examples/{}.b", buffer.trim())
+    });
+    let args = std::iter::once(path.clone()).chain(args).collect();
 
-    Interpreter::new().interpret(format!("examples/{}.b", buffer.trim()));
-}
+    let filesystem = match DirectoryFileSystem::new(&root) {
+        Ok(filesystem) => filesystem,
+        Err(e) => {
+            eprintln!("{}: {}", root, e);
+            exit(1);
+        }
+    };
 
-fn interpret_string(s: String) -> i64 {
-    Interpreter::new().interpret_string(s)
-}
\ No newline at end of file
+    let interpreter = Interpreter::new()
+        .with_word_size(word_size)
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            exit(1);
+        });
+    let mut interpreter = lints.into_iter().fold(
+        interpreter
+            .with_filesystem(filesystem)
+            .with_args(args)
+            .with_big_integers(big_integers)
+            .with_error_format(error_format)
+            .with_uninitialized(uninitialized),
+        |interpreter, (lint, level)| interpreter.with_lint_level(lint, level),
+    );
+    if let Some((time, seed)) = pinned {
+        interpreter = interpreter.with_environment(PinnedEnvironment::new(time, seed));
+    }
+    match interpreter.interpret(path) {
+        Ok(status) => exit(status as i32),
+        Err(e) => {
+            match error_format {
+                ErrorFormat::Human => eprintln!("{}", e),
+                ErrorFormat::Json => {
+                    for diagnostic in e.diagnostics() {
+                        eprintln!("{}", diagnostic.to_json());
+                    }
+                }
+            }
+            exit(1);
+        }
+    }
+}
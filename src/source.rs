@@ -0,0 +1,266 @@
+use std::fmt::{Display, Formatter};
+
+/// Which of the sources loaded into a `SourceMap` a span is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct FileId(pub usize);
+
+/// A byte range of one source file.
+///
+/// Nodes built by the interpreter rather than parsed, like the ones the
+/// `expression_constructors` helpers make out of values, get the empty
+/// default span, which is in no file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Span {
+    pub file: FileId,
+    pub start: usize,
+    pub end: usize,
+}
+impl Default for Span {
+    fn default() -> Self {
+        Self::new(FileId(usize::MAX), 0, 0)
+    }
+}
+impl Span {
+    pub fn new(file: FileId, start: usize, end: usize) -> Self {
+        Self { file, start, end }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The smallest span covering both, or whichever is not empty if they
+    /// are in different files
+    pub fn to(self, other: Span) -> Span {
+        if self.is_empty() || self.file != other.file {
+            return if other.is_empty() { self } else { other };
+        }
+        Span {
+            file: self.file,
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+/// Where a span is in a named source, looked up so it can be reported once
+/// the `SourceMap` is gone. Lines and columns are counted from 1, columns in
+/// characters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    /// The span's byte range in the source
+    pub start: usize,
+    pub end: usize,
+}
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.path, self.line, self.column)
+    }
+}
+
+#[derive(Debug)]
+pub struct SourceFile {
+    pub path: String,
+    pub source: String,
+    // Byte offset each line starts at
+    line_starts: Vec<usize>,
+}
+impl SourceFile {
+    pub fn new<P: ToString, S: ToString>(path: P, source: S) -> Self {
+        let source = source.to_string();
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            path: path.to_string(),
+            source,
+            line_starts,
+        }
+    }
+
+    /// The line and column of a byte offset, both counted from 1
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.source.len());
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let start = self.line_starts[line];
+        let column = self.source[start..offset].chars().count() + 1;
+        (line + 1, column)
+    }
+
+    /// The text of a line counted from 1, without its newline
+    pub fn line(&self, line: usize) -> Option<&str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .map(|next| next - 1)
+            .unwrap_or_else(|| self.source.len());
+        Some(self.source[start..end].trim_end_matches('\r'))
+    }
+}
+
+/// Every source a program was loaded from, the file it was started with and
+/// anything passed to `eval` since
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+impl SourceMap {
+    pub fn new() -> Self {
+        Self { files: vec![] }
+    }
+
+    pub fn add<P: ToString, S: ToString>(&mut self, path: P, source: S) -> FileId {
+        self.files.push(SourceFile::new(path, source));
+        FileId(self.files.len() - 1)
+    }
+
+    pub fn get(&self, file: FileId) -> Option<&SourceFile> {
+        self.files.get(file.0)
+    }
+
+    /// The source a span covers
+    pub fn text(&self, span: Span) -> Option<&str> {
+        self.get(span.file)?.source.get(span.start..span.end)
+    }
+
+    /// Where a span is, None for the span of a node the interpreter made up
+    pub fn location(&self, span: Span) -> Option<Location> {
+        let file = self.get(span.file)?;
+        let (line, column) = file.line_column(span.start);
+        let (end_line, end_column) = file.line_column(span.end);
+        Some(Location {
+            path: file.path.clone(),
+            line,
+            column,
+            end_line,
+            end_column,
+            start: span.start,
+            end: span.end,
+        })
+    }
+
+    /// The line a span starts on with the span underlined, like
+    ///
+    /// ```text
+    ///   |
+    /// 3 |     return 0;
+    ///   |     ^^^^^^
+    /// ```
+    ///
+    /// A span running past the end of its line is underlined to the end of
+    /// the line, an empty one gets a single caret.
+    pub fn snippet(&self, span: Span) -> Option<String> {
+        let location = self.location(span)?;
+        let file = self.get(span.file)?;
+        let text = file.line(location.line)?;
+
+        let end_column = if location.end_line == location.line {
+            location.end_column
+        } else {
+            text.chars().count() + 1
+        };
+        let width = end_column.saturating_sub(location.column).max(1);
+
+        // Tabs are kept so the carets line up however wide they are shown
+        let indent: String = text
+            .chars()
+            .take(location.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let gutter = " ".repeat(location.line.to_string().len());
+        Some(format!(
+            "{} |\n{} | {}\n{} | {}{}",
+            gutter,
+            location.line,
+            text,
+            gutter,
+            indent,
+            "^".repeat(width)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources(source: &str) -> (SourceMap, FileId) {
+        let mut sources = SourceMap::new();
+        sources.add("other.b", "");
+        let file = sources.add("test.b", source);
+        (sources, file)
+    }
+
+    #[test]
+    fn locations_count_lines_and_characters_from_one() {
+        let (sources, file) = sources("main() {\n  x = \"é\" + y;\n}\n");
+        let location = sources.location(Span::new(file, 20, 21)).unwrap();
+        assert_eq!(location.path, "test.b");
+        assert_eq!((location.line, location.column), (2, 11));
+        assert_eq!((location.end_line, location.end_column), (2, 12));
+        assert_eq!((location.start, location.end), (20, 21));
+        assert_eq!(location.to_string(), "test.b:2:11");
+
+        let whole = sources.location(Span::new(file, 0, 26)).unwrap();
+        assert_eq!((whole.line, whole.column), (1, 1));
+        assert_eq!((whole.end_line, whole.end_column), (3, 2));
+    }
+
+    #[test]
+    fn made_up_spans_have_no_location() {
+        let (sources, file) = sources("main() {}");
+        assert_eq!(sources.location(Span::default()), None);
+        assert_eq!(sources.location(Span::new(FileId(5), 1, 2)), None);
+        assert!(sources.location(Span::new(file, 3, 3)).is_some());
+        // The start of a file is somewhere, even with nothing in it
+        let location = sources.location(Span::new(file, 0, 0)).unwrap();
+        assert_eq!((location.line, location.column), (1, 1));
+        let (empty, file) = self::sources("");
+        assert!(empty.location(Span::new(file, 0, 0)).is_some());
+    }
+
+    #[test]
+    fn spans_cover_both_ends() {
+        let (a, b) = (Span::new(FileId(1), 4, 6), Span::new(FileId(1), 1, 3));
+        assert_eq!(a.to(b), Span::new(FileId(1), 1, 6));
+        assert_eq!(Span::default().to(a), a);
+        assert_eq!(a.to(Span::new(FileId(2), 0, 9)), Span::new(FileId(2), 0, 9));
+    }
+
+    #[test]
+    fn snippets_underline_the_span() {
+        let (sources, file) = sources("main() {\n\treturn x + 1;\n}\n");
+        assert_eq!(
+            sources.snippet(Span::new(file, 17, 22)).unwrap(),
+            "  |\n2 | \treturn x + 1;\n  | \t       ^^^^^"
+        );
+    }
+
+    #[test]
+    fn snippets_stop_at_the_end_of_the_line() {
+        let (sources, file) = sources("main() {\n  return 0;\n}\n");
+        let snippet = sources.snippet(Span::new(file, 0, 25)).unwrap();
+        assert_eq!(snippet, "  |\n1 | main() {\n  | ^^^^^^^^");
+        let empty = sources.snippet(Span::new(file, 11, 11)).unwrap();
+        assert!(empty.ends_with("\n  |   ^"));
+    }
+
+    #[test]
+    fn the_gutter_fits_the_line_number() {
+        let source = "\n".repeat(9) + "x;";
+        let (sources, file) = sources(&source);
+        assert_eq!(
+            sources.snippet(Span::new(file, 9, 10)).unwrap(),
+            "   |\n10 | x;\n   | ^"
+        );
+    }
+}